@@ -0,0 +1,249 @@
+//! Certificates of erasure signed with Ed25519.
+//!
+//! A certificate is a single line JSON document followed by a line with
+//! the algorithm, the public key and the signature of the document, in hex.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::crypto::{ed25519, from_hex, to_hex};
+use crate::{sys, Record, Result};
+
+const ALGORITHM: &str = "ed25519";
+const VERSION: u32 = 1;
+
+/// Private key, kept as a hex seed in a file readable by the owner only.
+pub struct SigningKey([u8; ed25519::SEED_LEN]);
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        let mut seed = [0; ed25519::SEED_LEN];
+        OsRng.fill_bytes(&mut seed);
+        Self(seed)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self(parse_hex(&fs::read_to_string(path)?)?))
+    }
+
+    /// Write the key to `path`, refusing to overwrite an existing file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        use std::io::Write;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        writeln!(options.open(path)?, "{}", to_hex(&self.0))?;
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(ed25519::public_key(&self.0))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey([u8; ed25519::PUBLIC_KEY_LEN]);
+
+impl PublicKey {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, format!("{}\n", self))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl std::str::FromStr for PublicKey {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self(parse_hex(s)?))
+    }
+}
+
+/// Records of a run, turned into signed text by [`Certificate::sign`].
+pub struct Certificate {
+    scheme: String,
+    records: Vec<Record>,
+}
+
+impl Certificate {
+    pub fn new(scheme: &str) -> Self {
+        Self {
+            scheme: scheme.into(),
+            records: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: Record) {
+        self.records.push(record);
+    }
+
+    pub fn sign(&self, key: &SigningKey) -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let body = format!(
+            r#"{{"version":{},"host":{},"user":{},"time":"{}","timestamp":{},"scheme":{},"records":[{}]}}"#,
+            VERSION,
+            crate::report::json_string(&sys::hostname()),
+            crate::report::json_string(&sys::username()),
+            format_time(timestamp),
+            timestamp,
+            crate::report::json_string(&self.scheme),
+            self.records
+                .iter()
+                .map(Record::to_json)
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        let signature = ed25519::sign(&key.0, body.as_bytes());
+        format!(
+            "{}\n{} {} {}\n",
+            body,
+            ALGORITHM,
+            key.public_key(),
+            to_hex(&signature),
+        )
+    }
+}
+
+/// Check that certificate `text` is signed with the trusted `key`. The key
+/// written in the certificate proves nothing by itself, anyone can sign
+/// with their own.
+pub fn verify(text: &str, key: &PublicKey) -> Result<()> {
+    let (body, signature) = text
+        .trim_end_matches('\n')
+        .rsplit_once('\n')
+        .ok_or("certificate: no signature")?;
+    let fields: Vec<&str> = signature.split(' ').collect();
+    if fields.len() != 3 || fields[0] != ALGORITHM {
+        return Err("certificate: invalid signature line".into());
+    }
+    let signer: PublicKey = fields[1].parse()?;
+    if *key != signer {
+        return Err(format!("certificate: signed with another key: {}", signer).into());
+    }
+    let signature = parse_hex(fields[2])?;
+    if !ed25519::verify(&signer.0, body.as_bytes(), &signature) {
+        return Err("certificate: bad signature".into());
+    }
+    Ok(())
+}
+
+fn parse_hex<const N: usize>(s: &str) -> Result<[u8; N]> {
+    from_hex(s.trim())?
+        .try_into()
+        .map_err(|_| format!("expected {} hex bytes", N).into())
+}
+
+/// RFC 3339 UTC time of a unix timestamp.
+fn format_time(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed() -> (SigningKey, String) {
+        let key = SigningKey::generate();
+        let mut cert = Certificate::new("nist");
+        cert.push(Record::new(Path::new("a"), crate::Kind::File));
+        let text = cert.sign(&key);
+        (key, text)
+    }
+
+    #[test]
+    fn time() {
+        let cases = [
+            (0, "1970-01-01T00:00:00Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (951_868_799, "2000-02-29T23:59:59Z"),
+            (1_709_164_800, "2024-02-29T00:00:00Z"),
+            (4_107_542_399, "2100-02-28T23:59:59Z"),
+            (4_107_542_400, "2100-03-01T00:00:00Z"),
+            (253_402_300_799, "9999-12-31T23:59:59Z"),
+        ];
+        for (timestamp, time) in &cases {
+            assert_eq!(format_time(*timestamp), *time);
+        }
+    }
+
+    #[test]
+    fn verify_with_signing_key() {
+        let (key, text) = signed();
+        verify(&text, &key.public_key()).unwrap();
+    }
+
+    #[test]
+    fn verify_with_other_key() {
+        let (_, text) = signed();
+        assert!(verify(&text, &SigningKey::generate().public_key()).is_err());
+    }
+
+    /// Signed again with another key, which the certificate then names.
+    #[test]
+    fn verify_forged() {
+        let (key, text) = signed();
+        let (body, _) = text.trim_end().rsplit_once('\n').unwrap();
+        let body = body.replace(r#""nist""#, r#""dod""#);
+        let forger = SigningKey::generate();
+        let signature = to_hex(&ed25519::sign(&forger.0, body.as_bytes()));
+        let forged = format!(
+            "{}\n{} {} {}\n",
+            body,
+            ALGORITHM,
+            forger.public_key(),
+            signature
+        );
+        verify(&forged, &forger.public_key()).unwrap();
+        assert!(verify(&forged, &key.public_key()).is_err());
+    }
+
+    #[test]
+    fn verify_tampered() {
+        let (key, text) = signed();
+        let text = text.replace(r#""nist""#, r#""dod""#);
+        assert!(verify(&text, &key.public_key()).is_err());
+    }
+}
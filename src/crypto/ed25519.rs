@@ -0,0 +1,455 @@
+//! Ed25519 signatures as specified in RFC 8032, after TweetNaCl.
+
+use std::convert::TryInto;
+
+use super::sha512::{self, Sha512};
+
+pub const SEED_LEN: usize = 32;
+pub const PUBLIC_KEY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Field element of GF(2^255 - 19) in 16 limbs of 16 bits.
+type Gf = [i64; 16];
+
+/// Point in extended coordinates (X, Y, Z, T).
+type Point = [Gf; 4];
+
+const GF0: Gf = [0; 16];
+const GF1: Gf = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+const D: Gf = [
+    0x78a3, 0x1359, 0x4dca, 0x75eb, 0xd8ab, 0x4141, 0x0a4d, 0x0070, 0xe898, 0x7779, 0x4079, 0x8cc7,
+    0xfe73, 0x2b6f, 0x6cee, 0x5203,
+];
+const D2: Gf = [
+    0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0, 0xd130, 0xeef3, 0x80f2, 0x198e,
+    0xfce7, 0x56df, 0xd9dc, 0x2406,
+];
+const X: Gf = [
+    0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c, 0xdc5c, 0xfdd6, 0xe231, 0xc0a4,
+    0x53fe, 0xcd6e, 0x36d3, 0x2169,
+];
+const Y: Gf = [
+    0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+    0x6666, 0x6666, 0x6666, 0x6666,
+];
+const I: Gf = [
+    0xa0b0, 0x4a0e, 0x1b27, 0xc4ee, 0xe478, 0xad2f, 0x1806, 0x2f43, 0xd7a7, 0x3dfb, 0x0099, 0x2b4d,
+    0xdf0b, 0x4fc1, 0x2480, 0x2b83,
+];
+
+/// Order of the base point.
+const L: [i64; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+fn car25519(o: &mut Gf) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+/// Swap `p` and `q` if `b` is 1, in constant time.
+fn sel25519(p: &mut Gf, q: &mut Gf, b: i64) {
+    let c = !(b - 1);
+    for i in 0..16 {
+        let t = c & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn pack25519(n: &Gf) -> [u8; 32] {
+    let mut t = *n;
+    car25519(&mut t);
+    car25519(&mut t);
+    car25519(&mut t);
+    for _ in 0..2 {
+        let mut m = GF0;
+        m[0] = t[0] - 0xffed;
+        for i in 1..15 {
+            m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xffff;
+        }
+        m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+        let b = (m[15] >> 16) & 1;
+        m[14] &= 0xffff;
+        sel25519(&mut t, &mut m, 1 - b);
+    }
+    let mut o = [0; 32];
+    for i in 0..16 {
+        o[2 * i] = t[i] as u8;
+        o[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+    o
+}
+
+fn neq25519(a: &Gf, b: &Gf) -> bool {
+    pack25519(a) != pack25519(b)
+}
+
+fn par25519(a: &Gf) -> u8 {
+    pack25519(a)[0] & 1
+}
+
+fn unpack25519(n: &[u8; 32]) -> Gf {
+    let mut o = GF0;
+    for i in 0..16 {
+        o[i] = n[2 * i] as i64 + ((n[2 * i + 1] as i64) << 8);
+    }
+    o[15] &= 0x7fff;
+    o
+}
+
+fn add(a: &Gf, b: &Gf) -> Gf {
+    let mut o = GF0;
+    for i in 0..16 {
+        o[i] = a[i] + b[i];
+    }
+    o
+}
+
+fn sub(a: &Gf, b: &Gf) -> Gf {
+    let mut o = GF0;
+    for i in 0..16 {
+        o[i] = a[i] - b[i];
+    }
+    o
+}
+
+fn mul(a: &Gf, b: &Gf) -> Gf {
+    let mut t = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+    let mut o = GF0;
+    o.copy_from_slice(&t[..16]);
+    car25519(&mut o);
+    car25519(&mut o);
+    o
+}
+
+fn sq(a: &Gf) -> Gf {
+    mul(a, a)
+}
+
+fn inv25519(i: &Gf) -> Gf {
+    let mut c = *i;
+    for a in (0..=253).rev() {
+        c = sq(&c);
+        if a != 2 && a != 4 {
+            c = mul(&c, i);
+        }
+    }
+    c
+}
+
+fn pow2523(i: &Gf) -> Gf {
+    let mut c = *i;
+    for a in (0..=250).rev() {
+        c = sq(&c);
+        if a != 1 {
+            c = mul(&c, i);
+        }
+    }
+    c
+}
+
+fn point_add(p: &mut Point, q: &Point) {
+    let a = mul(&sub(&p[1], &p[0]), &sub(&q[1], &q[0]));
+    let b = mul(&add(&p[0], &p[1]), &add(&q[0], &q[1]));
+    let c = mul(&mul(&p[3], &q[3]), &D2);
+    let d = mul(&p[2], &q[2]);
+    let d = add(&d, &d);
+    let e = sub(&b, &a);
+    let f = sub(&d, &c);
+    let g = add(&d, &c);
+    let h = add(&b, &a);
+    p[0] = mul(&e, &f);
+    p[1] = mul(&h, &g);
+    p[2] = mul(&g, &f);
+    p[3] = mul(&e, &h);
+}
+
+fn cswap(p: &mut Point, q: &mut Point, b: u8) {
+    for i in 0..4 {
+        sel25519(&mut p[i], &mut q[i], b as i64);
+    }
+}
+
+fn pack(p: &Point) -> [u8; 32] {
+    let zi = inv25519(&p[2]);
+    let tx = mul(&p[0], &zi);
+    let ty = mul(&p[1], &zi);
+    let mut r = pack25519(&ty);
+    r[31] ^= par25519(&tx) << 7;
+    r
+}
+
+fn scalarmult(q: &mut Point, s: &[u8]) -> Point {
+    let mut p = [GF0, GF1, GF1, GF0];
+    for i in (0..=255).rev() {
+        let b = (s[i / 8] >> (i & 7)) & 1;
+        cswap(&mut p, q, b);
+        let pc = p;
+        point_add(q, &pc);
+        point_add(&mut p, &pc);
+        cswap(&mut p, q, b);
+    }
+    p
+}
+
+fn scalarbase(s: &[u8]) -> Point {
+    let mut q = [X, Y, GF1, mul(&X, &Y)];
+    scalarmult(&mut q, s)
+}
+
+/// Negated point from its encoding, `None` if it is not on the curve.
+fn unpackneg(p: &[u8; 32]) -> Option<Point> {
+    let y = unpack25519(p);
+    let num = sq(&y);
+    let den = mul(&num, &D);
+    let num = sub(&num, &GF1);
+    let den = add(&GF1, &den);
+    let den2 = sq(&den);
+    let den4 = sq(&den2);
+    let den6 = mul(&den4, &den2);
+    let mut t = mul(&den6, &num);
+    t = mul(&t, &den);
+    t = pow2523(&t);
+    t = mul(&t, &num);
+    t = mul(&t, &den);
+    t = mul(&t, &den);
+    let mut x = mul(&t, &den);
+    if neq25519(&mul(&sq(&x), &den), &num) {
+        x = mul(&x, &I);
+    }
+    if neq25519(&mul(&sq(&x), &den), &num) {
+        return None;
+    }
+    if par25519(&x) == (p[31] >> 7) {
+        x = sub(&GF0, &x);
+    }
+    let t = mul(&x, &y);
+    Some([x, y, GF1, t])
+}
+
+fn mod_l(x: &mut [i64; 64]) -> [u8; 32] {
+    for i in (32..64).rev() {
+        let mut carry = 0;
+        let mut j = i - 32;
+        while j < i - 12 {
+            x[j] += carry - 16 * x[i] * L[j - (i - 32)];
+            carry = (x[j] + 128) >> 8;
+            x[j] -= carry << 8;
+            j += 1;
+        }
+        x[j] += carry;
+        x[i] = 0;
+    }
+    let mut carry = 0;
+    for j in 0..32 {
+        x[j] += carry - (x[31] >> 4) * L[j];
+        carry = x[j] >> 8;
+        x[j] &= 255;
+    }
+    for j in 0..32 {
+        x[j] -= carry * L[j];
+    }
+    let mut r = [0; 32];
+    for i in 0..32 {
+        x[i + 1] += x[i] >> 8;
+        r[i] = (x[i] & 255) as u8;
+    }
+    r
+}
+
+fn reduce(h: &[u8; 64]) -> [u8; 32] {
+    let mut x = [0i64; 64];
+    for i in 0..64 {
+        x[i] = h[i] as i64;
+    }
+    mod_l(&mut x)
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 64] {
+    let mut h = Sha512::new();
+    for part in parts {
+        h.update(part);
+    }
+    h.finalize()
+}
+
+fn expand(seed: &[u8; SEED_LEN]) -> [u8; 64] {
+    let mut d = sha512::hash(seed);
+    d[0] &= 248;
+    d[31] &= 127;
+    d[31] |= 64;
+    d
+}
+
+pub fn public_key(seed: &[u8; SEED_LEN]) -> [u8; PUBLIC_KEY_LEN] {
+    pack(&scalarbase(&expand(seed)[..32]))
+}
+
+pub fn sign(seed: &[u8; SEED_LEN], msg: &[u8]) -> [u8; SIGNATURE_LEN] {
+    let d = expand(seed);
+    let pk = pack(&scalarbase(&d[..32]));
+    let r = reduce(&hash(&[&d[32..], msg]));
+    let big_r = pack(&scalarbase(&r));
+    let h = reduce(&hash(&[&big_r, &pk, msg]));
+    let mut x = [0i64; 64];
+    for i in 0..32 {
+        x[i] = r[i] as i64;
+    }
+    for i in 0..32 {
+        for j in 0..32 {
+            x[i + j] += h[i] as i64 * d[j] as i64;
+        }
+    }
+    let s = mod_l(&mut x);
+    let mut sig = [0; SIGNATURE_LEN];
+    sig[..32].copy_from_slice(&big_r);
+    sig[32..].copy_from_slice(&s);
+    sig
+}
+
+/// Whether the scalar `s` is below the group order, as RFC 8032 5.1.7
+/// requires of the S half of a signature.
+fn is_canonical(s: &[u8; 32]) -> bool {
+    for i in (0..32).rev() {
+        let l = L[i] as u8;
+        if s[i] != l {
+            return s[i] < l;
+        }
+    }
+    false
+}
+
+pub fn verify(pk: &[u8; PUBLIC_KEY_LEN], msg: &[u8], sig: &[u8; SIGNATURE_LEN]) -> bool {
+    if !is_canonical(sig[32..].try_into().unwrap()) {
+        return false;
+    }
+    let mut q = match unpackneg(pk) {
+        Some(v) => v,
+        None => return false,
+    };
+    let h = reduce(&hash(&[&sig[..32], pk, msg]));
+    let mut p = scalarmult(&mut q, &h);
+    let q = scalarbase(&sig[32..]);
+    point_add(&mut p, &q);
+    pack(&p)[..] == sig[..32]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    use crate::crypto::from_hex;
+
+    fn array<const N: usize>(s: &str) -> [u8; N] {
+        from_hex(s).unwrap().try_into().unwrap()
+    }
+
+    /// Tests 1 to 3 of RFC 8032, section 7.1: seed, public key, message
+    /// and signature.
+    const CASES: [(&str, &str, &str, &str); 3] = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+        (
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        ),
+    ];
+
+    #[test]
+    fn known_answers() {
+        for (seed, pk, msg, sig) in &CASES {
+            let seed = array(seed);
+            let pk = array(pk);
+            let msg = from_hex(msg).unwrap();
+            let sig = array(sig);
+            assert_eq!(public_key(&seed), pk);
+            assert_eq!(sign(&seed, &msg), sig);
+            assert!(verify(&pk, &msg, &sig));
+        }
+    }
+
+    #[test]
+    fn tampered_message() {
+        let (_, pk, _, sig) = CASES[2];
+        assert!(!verify(&array(pk), &[0xaf, 0x83], &array(sig)));
+        assert!(!verify(&array(pk), &[0xaf], &array(sig)));
+    }
+
+    #[test]
+    fn tampered_signature() {
+        let (_, pk, msg, sig) = CASES[1];
+        let msg = from_hex(msg).unwrap();
+        for i in [0, 31, 32, 62] {
+            let mut sig = array(sig);
+            sig[i] ^= 1;
+            assert!(!verify(&array(pk), &msg, &sig));
+        }
+        // S not reduced, above the group order.
+        let mut sig = array(sig);
+        sig[63] |= 0xe0;
+        assert!(!verify(&array(pk), &msg, &sig));
+    }
+
+    #[test]
+    fn malleable_signature() {
+        let (_, pk, msg, sig) = CASES[1];
+        let msg = from_hex(msg).unwrap();
+        let mut sig = array(sig);
+        assert!(verify(&array(pk), &msg, &sig));
+        // S + L is the same scalar mod L, the equation would still hold.
+        let mut carry = 0;
+        for i in 0..32 {
+            let v = sig[32 + i] as i64 + L[i] + carry;
+            sig[32 + i] = v as u8;
+            carry = v >> 8;
+        }
+        assert_eq!(carry, 0);
+        assert!(!verify(&array(pk), &msg, &sig));
+        let mut l = [0; 32];
+        for i in 0..32 {
+            l[i] = L[i] as u8;
+        }
+        assert!(!is_canonical(&l));
+        l[0] -= 1;
+        assert!(is_canonical(&l));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let (_, _, msg, sig) = CASES[1];
+        let (_, other, _, _) = CASES[0];
+        assert!(!verify(&array(other), &from_hex(msg).unwrap(), &array(sig)));
+    }
+}
@@ -0,0 +1,22 @@
+//! Primitives for signed certificates, no external crates are pulled in.
+
+use crate::Result;
+
+pub mod ed25519;
+pub mod sha512;
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(format!("invalid hex: {}", s).into());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| format!("invalid hex: {}", s).into())
+        })
+        .collect()
+}
@@ -0,0 +1,243 @@
+//! Shell-like patterns to filter paths found while walking a tree.
+//!
+//! `*` matches anything but `/`, `**` anything, `?` a single character,
+//! `[a-z]` and `[!a-z]` a character of a class, `\` escapes. A pattern
+//! without `/` is matched against the file name, otherwise against the
+//! path relative to the top of the tree. A trailing `/` is ignored.
+
+use std::fmt;
+use std::mem;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    Any,
+    /// `*`, or `**` if it crosses directories.
+    Many(bool),
+    /// `**/`, any number of whole directories.
+    Dirs,
+    Class(bool, Vec<(char, char)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    text: String,
+    tokens: Vec<Token>,
+    has_slash: bool,
+}
+
+impl Glob {
+    pub fn matches(&self, path: &Path) -> bool {
+        let s = if self.has_slash {
+            path.to_string_lossy()
+        } else {
+            match path.file_name() {
+                Some(v) => v.to_string_lossy(),
+                None => return false,
+            }
+        };
+        let s: Vec<char> = s.chars().collect();
+        match_all(&self.tokens, &s)
+    }
+}
+
+/// Whether `tokens` match all of `s`. Goes from the last token to the
+/// first, keeping for every position of `s` whether the rest matches from
+/// there, so the time is linear in both lengths.
+fn match_all(tokens: &[Token], s: &[char]) -> bool {
+    let n = s.len();
+    let mut next: Vec<bool> = (0..=n).map(|j| j == n).collect();
+    let mut cur = vec![false; n + 1];
+    for token in tokens.iter().rev() {
+        // Some `/` at or after the position is followed by a match.
+        let mut after_slash = false;
+        for j in (0..=n).rev() {
+            let c = s.get(j).copied();
+            cur[j] = match token {
+                Token::Char(v) => c == Some(*v) && next[j + 1],
+                Token::Any => c.is_some_and(|c| c != '/') && next[j + 1],
+                Token::Many(cross) => {
+                    next[j] || c.is_some_and(|c| *cross || c != '/') && cur[j + 1]
+                }
+                Token::Dirs => {
+                    after_slash |= c == Some('/') && next[j + 1];
+                    next[j] || after_slash
+                }
+                Token::Class(negate, ranges) => {
+                    c.is_some_and(|c| {
+                        c != '/' && ranges.iter().any(|&(a, b)| a <= c && c <= b) != *negate
+                    }) && next[j + 1]
+                }
+            };
+        }
+        mem::swap(&mut next, &mut cur);
+    }
+    next[0]
+}
+
+/// Parse a class after `[`, returning it with the number of characters
+/// used, or `None` if it is not closed.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut pos = 0;
+    let negate = matches!(chars.first(), Some('!') | Some('^'));
+    if negate {
+        pos += 1;
+    }
+    let mut ranges = Vec::new();
+    let start = pos;
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c == ']' && pos > start {
+            return Some((Token::Class(negate, ranges), pos + 1));
+        }
+        if pos + 2 < chars.len() && chars[pos + 1] == '-' && chars[pos + 2] != ']' {
+            ranges.push((c, chars[pos + 2]));
+            pos += 3;
+        } else {
+            ranges.push((c, c));
+            pos += 1;
+        }
+    }
+    None
+}
+
+impl FromStr for Glob {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim_end_matches('/');
+        let pattern = trimmed.strip_prefix('/').unwrap_or(trimmed);
+        if pattern.is_empty() {
+            return Err(format!("empty glob: {}", s).into());
+        }
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        while pos < chars.len() {
+            let token = match chars[pos] {
+                '\\' if pos + 1 < chars.len() => {
+                    pos += 1;
+                    Token::Char(chars[pos])
+                }
+                '?' => Token::Any,
+                '*' if chars.get(pos + 1) == Some(&'*') => {
+                    pos += 1;
+                    if chars.get(pos + 1) == Some(&'/') {
+                        pos += 1;
+                        Token::Dirs
+                    } else {
+                        Token::Many(true)
+                    }
+                }
+                '*' => Token::Many(false),
+                '[' => match parse_class(&chars[pos + 1..]) {
+                    Some((token, n)) => {
+                        pos += n;
+                        token
+                    }
+                    None => Token::Char('['),
+                },
+                c => Token::Char(c),
+            };
+            tokens.push(token);
+            pos += 1;
+        }
+        Ok(Self {
+            text: s.into(),
+            has_slash: trimmed.contains('/'),
+            tokens,
+        })
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        pattern.parse::<Glob>().unwrap().matches(Path::new(path))
+    }
+
+    #[test]
+    fn star() {
+        assert!(matches("*.log", "a.log"));
+        assert!(matches("*.log", ".log"));
+        assert!(matches("*.log", "dir/a.log"));
+        assert!(!matches("*.log", "a.log.1"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxbyy"));
+        assert!(!matches("dir/*.log", "dir/sub/a.log"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(matches("**/a.log", "a.log"));
+        assert!(matches("**/a.log", "x/y/a.log"));
+        assert!(!matches("**/a.log", "x/ya.log"));
+        assert!(matches("x/**/a", "x/a"));
+        assert!(matches("x/**/a", "x/y/z/a"));
+        assert!(!matches("x/**/a", "xy/a"));
+        assert!(matches("x/**", "x/y/z"));
+        assert!(matches("x/**z", "x/y/z"));
+        assert!(!matches("x/*z", "x/y/z"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(!matches("a?c", "a/c"));
+    }
+
+    #[test]
+    fn class() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[a-c]x", "dx"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(matches("[^a-c]x", "dx"));
+        assert!(!matches("[!a-c]x", "ax"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[ab", "[ab"));
+        assert!(!matches("x[!a]y", "x/y"));
+    }
+
+    #[test]
+    fn escape() {
+        assert!(matches(r"\*", "*"));
+        assert!(!matches(r"\*", "a"));
+        assert!(matches(r"a\?", "a?"));
+        assert!(matches(r"\[a]", "[a]"));
+    }
+
+    #[test]
+    fn slash() {
+        assert!(matches("a/b", "a/b"));
+        assert!(!matches("a/b", "x/a/b"));
+        assert!(matches("/a/b", "a/b"));
+        assert!(matches("a/", "x/a"));
+        assert!(matches("b", "a/b"));
+        assert!(!matches("b", "b/a"));
+        assert!("/".parse::<Glob>().is_err());
+        assert!("".parse::<Glob>().is_err());
+    }
+
+    #[test]
+    fn many_stars_are_fast() {
+        let name = "a".repeat(200);
+        assert!(!matches("*a*a*a*a*a*a*a*b", &name));
+        assert!(matches("*a*a*a*a*a*a*a*", &name));
+        assert!(!matches("**/**/**/**/**/**/b", &"a/".repeat(100)));
+    }
+}
@@ -0,0 +1,2123 @@
+//! Wipe files with randomized ASCII dicks.
+//!
+//! ```no_run
+//! let mut wiper = viper::WipeConfig::new().zero(true).num_rounds(3).build();
+//! let report = wiper.wipe_tree("./delete_me");
+//! assert!(report.is_ok());
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs::{self, FileTimes};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::result;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub mod cert;
+mod crypto;
+pub mod glob;
+pub mod pattern;
+pub mod progress;
+pub mod report;
+pub mod scheme;
+mod sys;
+#[cfg(test)]
+mod testing;
+
+pub use glob::Glob;
+pub use pattern::{Pattern, PatternSource};
+pub use report::{Kind, Record, Report};
+pub use scheme::Scheme;
+
+pub type Error = Box<dyn error::Error + Send + Sync>;
+pub type Result<T> = result::Result<T, Error>;
+
+pub mod default {
+    pub const NUM_ROUNDS: u32 = 1;
+    pub const BLOCK_SIZE: usize = 8 << 20;
+    pub const JOBS: usize = 1;
+    pub const RENAMES: u32 = 1;
+    /// Never wiped with `preserve_root`, together with the home directory.
+    pub const PROTECTED: [&str; 28] = [
+        "/",
+        "/bin",
+        "/boot",
+        "/dev",
+        "/etc",
+        "/home",
+        "/lib",
+        "/lib32",
+        "/lib64",
+        "/media",
+        "/mnt",
+        "/opt",
+        "/proc",
+        "/root",
+        "/run",
+        "/sbin",
+        "/srv",
+        "/sys",
+        "/tmp",
+        "/usr",
+        "/usr/bin",
+        "/usr/lib",
+        "/usr/local",
+        "/usr/sbin",
+        "/var",
+        "/Applications",
+        "/System",
+        "/Users",
+    ];
+    /// Maximum size of a single file made by free space wiping.
+    pub const FILLER_SIZE: u64 = 1 << 30;
+}
+
+/// Options of a wipe run, turned into a [`Wiper`] by [`WipeConfig::build`].
+#[derive(Debug, Clone)]
+pub struct WipeConfig {
+    recursive: bool,
+    zero: bool,
+    num_rounds: u32,
+    block_size: usize,
+    patterns: Vec<Pattern>,
+    verify: Verify,
+    symlinks: Symlinks,
+    hardlinks: Hardlinks,
+    holes: Holes,
+    direct: bool,
+    dry_run: bool,
+    hash: bool,
+    jobs: usize,
+    renames: u32,
+    truncate: bool,
+    timestamps: Timestamps,
+    strict: bool,
+    keep: bool,
+    zero_last: bool,
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    older_than: Option<Duration>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    owner: Option<u32>,
+    one_file_system: bool,
+    preserve_root: bool,
+    protected: Vec<PathBuf>,
+}
+
+impl Default for WipeConfig {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            zero: false,
+            num_rounds: default::NUM_ROUNDS,
+            block_size: default::BLOCK_SIZE,
+            patterns: Vec::new(),
+            verify: Verify::Never,
+            symlinks: Symlinks::Unlink,
+            hardlinks: Hardlinks::WipeOnce,
+            holes: Holes::Fill,
+            direct: false,
+            dry_run: false,
+            hash: false,
+            jobs: default::JOBS,
+            renames: default::RENAMES,
+            truncate: false,
+            timestamps: Timestamps::Keep,
+            strict: false,
+            keep: false,
+            zero_last: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            older_than: None,
+            min_size: None,
+            max_size: None,
+            owner: None,
+            one_file_system: false,
+            preserve_root: true,
+            protected: Vec::new(),
+        }
+    }
+}
+
+impl WipeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Walk directories recursively.
+    pub fn recursive(mut self, value: bool) -> Self {
+        self.recursive = value;
+        self
+    }
+
+    /// First overwrite with zeroes.
+    pub fn zero(mut self, value: bool) -> Self {
+        self.zero = value;
+        self
+    }
+
+    /// Number of rounds to overwrite, zero means default.
+    pub fn num_rounds(mut self, value: u32) -> Self {
+        self.num_rounds = if value == 0 {
+            default::NUM_ROUNDS
+        } else {
+            value
+        };
+        self
+    }
+
+    /// Maximum block size in bytes, zero means default.
+    pub fn block_size(mut self, value: usize) -> Self {
+        self.block_size = if value == 0 {
+            default::BLOCK_SIZE
+        } else {
+            value
+        };
+        self
+    }
+
+    /// Pattern of every round, overrides `zero` and `num_rounds`.
+    pub fn patterns(mut self, value: Vec<Pattern>) -> Self {
+        self.patterns = value;
+        self
+    }
+
+    /// Passes of a standard scheme, overrides `zero` and `num_rounds`.
+    pub fn scheme(mut self, value: Scheme) -> Self {
+        self.patterns = value.patterns();
+        self
+    }
+
+    /// Read the file back and compare with what was written.
+    pub fn verify(mut self, value: Verify) -> Self {
+        self.verify = value;
+        self
+    }
+
+    /// What to do with symbolic links.
+    pub fn symlinks(mut self, value: Symlinks) -> Self {
+        self.symlinks = value;
+        self
+    }
+
+    /// What to do with files found by the walk that have more than one
+    /// name.
+    pub fn hardlinks(mut self, value: Hardlinks) -> Self {
+        self.hardlinks = value;
+        self
+    }
+
+    /// What to do with holes of sparse files.
+    pub fn holes(mut self, value: Holes) -> Self {
+        self.holes = value;
+        self
+    }
+
+    /// Write rounds with direct I/O, so every one of them reaches the
+    /// device instead of the page cache.
+    pub fn direct(mut self, value: bool) -> Self {
+        self.direct = value;
+        self
+    }
+
+    /// Walk and report as usual, but do not open, rename or remove anything.
+    pub fn dry_run(mut self, value: bool) -> Self {
+        self.dry_run = value;
+        self
+    }
+
+    /// Hash the content with SHA-512 before the first round.
+    pub fn hash(mut self, value: bool) -> Self {
+        self.hash = value;
+        self
+    }
+
+    /// Number of threads wiping files of a tree, zero means default.
+    pub fn jobs(mut self, value: usize) -> Self {
+        self.jobs = if value == 0 { default::JOBS } else { value };
+        self
+    }
+
+    /// Number of times to rename a file or a directory to a random name of
+    /// the same length before removal, zero means default.
+    pub fn renames(mut self, value: u32) -> Self {
+        self.renames = if value == 0 { default::RENAMES } else { value };
+        self
+    }
+
+    /// Truncate a file to zero length before removal.
+    pub fn truncate(mut self, value: bool) -> Self {
+        self.truncate = value;
+        self
+    }
+
+    /// Reset access and modification times of a file before removal.
+    pub fn timestamps(mut self, value: Timestamps) -> Self {
+        self.timestamps = value;
+        self
+    }
+
+    /// Fail on empty files, instead of removing them with nothing to
+    /// overwrite.
+    pub fn strict(mut self, value: bool) -> Self {
+        self.strict = value;
+        self
+    }
+
+    /// Overwrite files, but leave them, links and directories in place.
+    pub fn keep(mut self, value: bool) -> Self {
+        self.keep = value;
+        self
+    }
+
+    /// Finally overwrite with zeroes, unless the last round already does.
+    pub fn zero_last(mut self, value: bool) -> Self {
+        self.zero_last = value;
+        self
+    }
+
+    /// Inside a tree, only wipe what matches any of the patterns, or is in
+    /// a directory that does. Everything else is left in place, together
+    /// with the directories holding it.
+    pub fn include(mut self, value: Vec<Glob>) -> Self {
+        self.include = value;
+        self
+    }
+
+    /// Inside a tree, leave in place what matches any of the patterns,
+    /// directories are not walked into.
+    pub fn exclude(mut self, value: Vec<Glob>) -> Self {
+        self.exclude = value;
+        self
+    }
+
+    /// Only wipe files last modified at least this long ago.
+    pub fn older_than(mut self, value: Duration) -> Self {
+        self.older_than = Some(value);
+        self
+    }
+
+    /// Only wipe files of at least this many bytes.
+    pub fn min_size(mut self, value: u64) -> Self {
+        self.min_size = Some(value);
+        self
+    }
+
+    /// Only wipe files of at most this many bytes.
+    pub fn max_size(mut self, value: u64) -> Self {
+        self.max_size = Some(value);
+        self
+    }
+
+    /// Only wipe files owned by this uid, files are never owned by anyone
+    /// where it is not supported.
+    pub fn owner(mut self, value: u32) -> Self {
+        self.owner = Some(value);
+        self
+    }
+
+    /// Leave in place what is on other filesystems than the top of a tree.
+    pub fn one_file_system(mut self, value: bool) -> Self {
+        self.one_file_system = value;
+        self
+    }
+
+    /// Refuse to wipe [`default::PROTECTED`] paths and the home directory.
+    pub fn preserve_root(mut self, value: bool) -> Self {
+        self.preserve_root = value;
+        self
+    }
+
+    /// Refuse to wipe these paths, whatever `preserve_root` is.
+    pub fn protected(mut self, value: Vec<PathBuf>) -> Self {
+        self.protected = value;
+        self
+    }
+
+    /// Paths refused by `preserve_root` and `protected`, canonical where
+    /// they exist.
+    fn protected_paths(&self) -> Vec<PathBuf> {
+        let mut res = Vec::new();
+        if self.preserve_root {
+            res.extend(default::PROTECTED.iter().map(PathBuf::from));
+            res.extend(env::var_os("HOME").map(PathBuf::from));
+        }
+        res.extend(self.protected.iter().cloned());
+        res.iter()
+            .map(|v| v.canonicalize().unwrap_or_else(|_| v.clone()))
+            .collect()
+    }
+
+    /// Patterns of all rounds in order.
+    pub fn rounds(&self) -> Vec<Pattern> {
+        let mut res = if !self.patterns.is_empty() {
+            self.patterns.clone()
+        } else {
+            let mut res = Vec::with_capacity(self.num_rounds as usize + 2);
+            if self.zero {
+                res.push(Pattern::Zero);
+            }
+            res.extend((0..self.num_rounds).map(|_| Pattern::Ascii));
+            res
+        };
+        if self.zero_last && res.last() != Some(&Pattern::Zero) {
+            res.push(Pattern::Zero);
+        }
+        res
+    }
+
+    pub fn build(self) -> Wiper {
+        Wiper::new(self)
+    }
+}
+
+/// After which rounds to read the file back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verify {
+    Never,
+    Last,
+    All,
+}
+
+impl FromStr for Verify {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never" => Ok(Self::Never),
+            "last" => Ok(Self::Last),
+            "all" => Ok(Self::All),
+            _ => Err(format!("unknown verify mode: {}", s).into()),
+        }
+    }
+}
+
+/// What to do with symbolic links, both given and found while walking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Leave the link alone.
+    Skip,
+    /// Remove the link itself, the target is not touched.
+    Unlink,
+    /// Wipe the target of a given link, then remove the link. Inside a
+    /// tree the link is removed only, if it points into the tree, which
+    /// is wiped anyway.
+    Follow,
+}
+
+impl FromStr for Symlinks {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "unlink" => Ok(Self::Unlink),
+            "follow" => Ok(Self::Follow),
+            _ => Err(format!("unknown symlinks policy: {}", s).into()),
+        }
+    }
+}
+
+/// What to do with files found by the walk that have more than one name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hardlinks {
+    /// Wipe the content at the last name once all of them are found in the
+    /// tree, only remove the others. With names elsewhere the content is
+    /// left to them and reported.
+    WipeOnce,
+    /// Wipe the content at the first name, even if names outside the tree
+    /// still point to it.
+    WipeShared,
+    /// Leave all names in place.
+    Skip,
+    /// Remove the name, the content is left to the other names.
+    UnlinkOnly,
+}
+
+impl FromStr for Hardlinks {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wipe-once" => Ok(Self::WipeOnce),
+            "wipe-shared" => Ok(Self::WipeShared),
+            "skip" => Ok(Self::Skip),
+            "unlink-only" => Ok(Self::UnlinkOnly),
+            _ => Err(format!("unknown hardlinks policy: {}", s).into()),
+        }
+    }
+}
+
+/// What to do with holes of sparse files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Holes {
+    /// Overwrite them as well, the file ends up fully allocated.
+    Fill,
+    /// Only overwrite ranges with data. Blocks preallocated but never
+    /// written may count as holes.
+    Skip,
+}
+
+impl FromStr for Holes {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fill" => Ok(Self::Fill),
+            "skip" => Ok(Self::Skip),
+            _ => Err(format!("unknown holes mode: {}", s).into()),
+        }
+    }
+}
+
+/// What to set access and modification times of a file to before removal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamps {
+    Keep,
+    /// 1970-01-01 00:00:00 UTC.
+    Epoch,
+    /// Random time between the epoch and now.
+    Random,
+}
+
+impl FromStr for Timestamps {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "epoch" => Ok(Self::Epoch),
+            "random" => Ok(Self::Random),
+            _ => Err(format!("unknown timestamps mode: {}", s).into()),
+        }
+    }
+}
+
+/// What the [`Wiper`] is doing right now, passed to the listener.
+#[derive(Debug, Clone, Copy)]
+pub enum Event<'a> {
+    Wipe(&'a Path),
+    Round(&'a Path, u32),
+    /// Bytes written so far on the round, out of the file size.
+    Progress(&'a Path, u32, u64, u64),
+    /// Round read back, with the number of mismatched bytes.
+    Verify(&'a Path, u32, u64),
+    Unlink(&'a Path),
+    RemoveDir(&'a Path),
+    Skip(&'a Path),
+    /// A target is done with, successfully or not.
+    Record(&'a Record),
+}
+
+type Listener = Arc<dyn Fn(Event) + Send + Sync>;
+type Confirm = Arc<dyn Fn(Event) -> bool + Send + Sync>;
+
+pub struct Wiper {
+    config: WipeConfig,
+    sources: Vec<Box<dyn PatternSource>>,
+    names: Vec<String>,
+    block: sys::AlignedBuf,
+    read_buf: Vec<u8>,
+    values: Vec<String>,
+    rng: ThreadRng,
+    listener: Option<Listener>,
+    confirm: Option<Confirm>,
+    protected: Vec<PathBuf>,
+    /// Files with more than one name found by the current walk, by device
+    /// and inode.
+    linked: HashMap<(u64, u64), Linked>,
+}
+
+impl Default for Wiper {
+    fn default() -> Self {
+        Self::new(WipeConfig::default())
+    }
+}
+
+impl Wiper {
+    pub fn new(config: WipeConfig) -> Self {
+        let rounds = config.rounds();
+        let sources = rounds.iter().map(Pattern::source).collect();
+        let names = rounds.iter().map(Pattern::to_string).collect();
+        let values = pattern::make_values();
+        assert!(!values.is_empty());
+        Self {
+            protected: config.protected_paths(),
+            linked: HashMap::new(),
+            config,
+            sources,
+            names,
+            block: sys::AlignedBuf::default(),
+            read_buf: Vec::new(),
+            values,
+            rng: rand::thread_rng(),
+            listener: None,
+            confirm: None,
+        }
+    }
+
+    pub fn config(&self) -> &WipeConfig {
+        &self.config
+    }
+
+    /// Call `f` on every [`Event`].
+    pub fn with_listener<F: Fn(Event) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.listener = Some(Arc::new(f));
+        self
+    }
+
+    /// Ask `f` before wiping a file, removing a directory or a link while
+    /// walking, the path is left in place if it returns `false`.
+    pub fn with_confirm<F: Fn(Event) -> bool + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.confirm = Some(Arc::new(f));
+        self
+    }
+
+    /// Replace the rounds built from the config with custom sources.
+    /// They can not be shared by workers, so `jobs` is reset to one.
+    pub fn with_sources(mut self, sources: Vec<Box<dyn PatternSource>>) -> Self {
+        assert!(!sources.is_empty());
+        self.config.jobs = 1;
+        self.names = vec!["custom".into(); sources.len()];
+        self.sources = sources;
+        self
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(f) = &self.listener {
+            f(event);
+        }
+    }
+
+    /// Emit the record of a target with the outcome.
+    fn finish<T, E: fmt::Display>(&self, mut record: Record, res: &result::Result<T, E>) {
+        if let Err(err) = res {
+            record.error = Some(err.to_string());
+        }
+        record.elapsed = record.started.elapsed().unwrap_or_default();
+        self.emit(Event::Record(&record));
+    }
+
+    /// Overwrite, rename and remove a single file, returning its size.
+    /// Block devices, and all files with `keep`, are overwritten only, they
+    /// stay in place.
+    pub fn wipe_file<P: AsRef<Path>>(&mut self, path: P) -> Result<u64> {
+        self.wipe_checked(path.as_ref()).map(|(size, _)| size)
+    }
+
+    /// Same as [`Wiper::wipe_file`], also telling if the file was read
+    /// back without mismatches.
+    fn wipe_checked(&mut self, path: &Path) -> Result<(u64, bool)> {
+        let mut record = Record::new(path, Kind::File);
+        let res = self.wipe_file1(path, &mut record);
+        let is_verified = record.verified == Some(true);
+        self.finish(record, &res);
+        res.map(|size| (size, is_verified))
+    }
+
+    fn wipe_file1(&mut self, path: &Path, record: &mut Record) -> Result<u64> {
+        self.emit(Event::Wipe(path));
+        let metadata = sys::at(path)?.metadata()?;
+        let is_device = sys::is_block_device(&metadata.file_type());
+        if is_device {
+            record.kind = Kind::Device;
+        }
+        if self.config.dry_run {
+            record.size = sys::size(&mut sys::at(path)?.open_read()?)?;
+            return Ok(record.size);
+        }
+        if self.config.hash {
+            record.sha512 = Some(self.hash(path)?);
+        }
+        if is_device || metadata.len() != 0 || self.config.strict {
+            record.size = self.rounds(path, 0, record)?;
+        }
+        if is_device || self.config.keep {
+            return Ok(record.size);
+        }
+        self.scrub(path)?;
+        let new_path = self.rename(path)?;
+        sys::at(&new_path)?.remove_file()?;
+        record.renamed = Some(new_path);
+        record.removed = true;
+        Ok(record.size)
+    }
+
+    /// Truncate the file and reset its timestamps, as configured, so that
+    /// less is left in the journal when it is renamed and removed.
+    fn scrub(&mut self, path: &Path) -> Result<()> {
+        let time = match self.config.timestamps {
+            Timestamps::Keep if !self.config.truncate => return Ok(()),
+            Timestamps::Keep => None,
+            Timestamps::Epoch => Some(UNIX_EPOCH),
+            Timestamps::Random => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                Some(UNIX_EPOCH + Duration::from_secs(self.rng.gen_range(0..now.as_secs().max(1))))
+            }
+        };
+        let file = sys::at(path)?.open_write()?;
+        if self.config.truncate {
+            file.set_len(0)?;
+        }
+        if let Some(time) = time {
+            file.set_times(FileTimes::new().set_accessed(time).set_modified(time))?;
+        }
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Rename `path` a few times to random names of the same length,
+    /// returning the last one.
+    fn rename(&mut self, path: &Path) -> Result<PathBuf> {
+        let mut path = path.to_path_buf();
+        let len = match path.file_name() {
+            Some(v) => v.len(),
+            None => return Ok(path),
+        };
+        for _ in 0..self.config.renames {
+            path = self.rename_free(&path, len)?;
+        }
+        Ok(path)
+    }
+
+    /// Rename `path` to a random name of `len` bytes next to it, never
+    /// replacing a taken one, even if taken by another worker right now.
+    /// Short names run out fast, so it grows by a byte every few tries.
+    fn rename_free(&mut self, path: &Path, len: usize) -> Result<PathBuf> {
+        const TRIES: usize = 8;
+        const MAX_TRIES: usize = 32 * TRIES;
+
+        let mut buf = Vec::with_capacity(len);
+        for n in 0..MAX_TRIES {
+            buf.resize(len + n / TRIES, 0);
+            pattern::fill_ascii(&self.values, &mut self.rng, &mut buf);
+            let new_path = path.with_file_name(String::from_utf8_lossy(&buf).as_ref());
+            match sys::rename_noreplace(&sys::at(path)?, &sys::at(&new_path)?) {
+                Ok(()) => return Ok(new_path),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Err(format!("{}: no free name to rename to", path.display()).into())
+    }
+
+    /// Fill free space of the filesystem containing `dir` with files until
+    /// it runs out, wipe and remove them. Returns the number of bytes covered.
+    pub fn wipe_free_space<P: AsRef<Path>>(&mut self, dir: P) -> Result<u64> {
+        let dir = dir.as_ref();
+        let mut record = Record::new(dir, Kind::FreeSpace);
+        let res = self.wipe_free_space1(dir, &mut record);
+        if let Ok(size) = res {
+            record.size = size;
+        }
+        self.finish(record, &res);
+        res
+    }
+
+    fn wipe_free_space1(&mut self, dir: &Path, record: &mut Record) -> Result<u64> {
+        if self.config.dry_run {
+            return Ok(sys::free_space(dir)?);
+        }
+        let mut fillers = Vec::new();
+        let mut res = self.fill(dir, &mut fillers, record);
+        for path in &fillers {
+            if res.is_err() {
+                break;
+            }
+            if let Err(err) = self.rounds(path, 1, record) {
+                res = Err(err);
+            }
+        }
+        for path in &fillers {
+            if let Err(err) = fs::remove_file(path) {
+                if res.is_ok() {
+                    res = Err(err.into());
+                }
+            }
+        }
+        record.removed = res.is_ok();
+        res
+    }
+
+    /// Create files in `dir` with the first round pattern until the space
+    /// runs out. Names are numbered, taken ones are passed over a few times.
+    fn fill(&mut self, dir: &Path, fillers: &mut Vec<PathBuf>, record: &mut Record) -> Result<u64> {
+        const TRIES: u32 = 64;
+
+        self.prepare_block(0, self.config.block_size as u64, u64::MAX);
+        let mut total = 0;
+        let mut tries = 0;
+        for n in 0.. {
+            let name = format!("{}{}", self.values.choose(&mut self.rng).unwrap(), n);
+            let path = dir.join(name);
+            let mut file = match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(v) => v,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    tries += 1;
+                    if tries == TRIES {
+                        return Err(format!("{}: no free name for a filler", dir.display()).into());
+                    }
+                    continue;
+                }
+                Err(err) if is_no_space(&err) => break,
+                Err(err) => return Err(err.into()),
+            };
+            tries = 0;
+            fillers.push(path.clone());
+            self.emit(Event::Wipe(&path));
+            self.emit(Event::Round(&path, 0));
+            let mut size = 0;
+            let mut pos = 0;
+            let mut is_full = false;
+            while size < default::FILLER_SIZE {
+                match file.write(&self.block[pos..]) {
+                    Ok(0) => is_full = true,
+                    Ok(n) => {
+                        size += n as u64;
+                        pos = (pos + n) % self.block.len();
+                        self.emit(Event::Progress(&path, 0, size, default::FILLER_SIZE));
+                    }
+                    Err(err) if is_no_space(&err) => is_full = true,
+                    Err(err) => return Err(err.into()),
+                }
+                if is_full {
+                    break;
+                }
+            }
+            if size < default::FILLER_SIZE {
+                self.emit(Event::Progress(&path, 0, size, size));
+            }
+            file.sync_all()?;
+            if size == 0 {
+                fillers.pop();
+                fs::remove_file(&path)?;
+                break;
+            }
+            record.patterns = vec![self.names[0].clone()];
+            if self.should_verify(0) {
+                self.check(&path, 0, size, record)?;
+            }
+            total += size;
+            if is_full {
+                break;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Run rounds starting from `from`, returning the file size.
+    fn rounds(&mut self, path: &Path, from: u32, record: &mut Record) -> Result<u64> {
+        let mut size = 0;
+        for n in from..self.sources.len() as u32 {
+            self.emit(Event::Round(path, n));
+            size = self.wipe(path, n)?;
+            record.patterns.truncate(n as usize);
+            record.patterns.push(self.names[n as usize].clone());
+            if self.should_verify(n) {
+                self.check(path, n, size, record)?;
+            }
+        }
+        Ok(size)
+    }
+
+    fn should_verify(&self, round: u32) -> bool {
+        match self.config.verify {
+            Verify::Never => false,
+            Verify::Last => round as usize == self.sources.len() - 1,
+            Verify::All => true,
+        }
+    }
+
+    fn check(&mut self, path: &Path, round: u32, size: u64, record: &mut Record) -> Result<()> {
+        let mismatches = self.verify(path, size)?;
+        self.emit(Event::Verify(path, round, mismatches));
+        record.verified = Some(mismatches == 0);
+        record.mismatches += mismatches;
+        if mismatches != 0 {
+            return Err(format!(
+                "file: {} verify failed on round {}: {} bytes mismatch",
+                path.display(),
+                round + 1,
+                mismatches,
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Fill the block for `round`, at most `max_size` long, but no longer
+    /// than `file_size`. With `direct` a repeated block keeps aligned.
+    fn prepare_block(&mut self, round: u32, max_size: u64, file_size: u64) {
+        let source = &mut self.sources[round as usize];
+        let mut block_size = file_size.min(max_size);
+        if block_size < file_size {
+            let mut period = source.period() as u64;
+            if self.config.direct {
+                period = lcm(period, sys::DIRECT_ALIGN as u64);
+            }
+            block_size = (block_size - block_size % period).max(period);
+        }
+        self.block.resize(block_size as usize);
+        source.fill(round, &mut self.block);
+    }
+
+    /// Wipe a file or, if recursive, a directory with all its content.
+    /// Errors do not stop the walk, they are collected into the report.
+    ///
+    /// With more than one job the walk goes on in this thread, while files
+    /// are wiped by a pool of workers, each with its own pattern sources.
+    /// A directory is removed by whoever is done with its last child.
+    pub fn wipe_tree<P: AsRef<Path>>(&mut self, path: P) -> Report {
+        let path = path.as_ref();
+        let root = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let dev = fs::metadata(path).ok().and_then(|v| sys::device(&v));
+        let mut report = Report::default();
+        let mut walk = Walk {
+            stack: vec![Entry {
+                path: path.to_path_buf(),
+                depth: 0,
+                parent: None,
+            }],
+            visited: HashSet::new(),
+            top: path,
+            root: &root,
+            dev,
+            queue: None,
+        };
+        if self.config.jobs <= 1 {
+            self.walk(&mut walk, &mut report);
+            return report;
+        }
+        let (queue, jobs) = mpsc::channel();
+        let jobs = Mutex::new(jobs);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.config.jobs)
+                .map(|_| {
+                    let config = self.config.clone();
+                    let listener = self.listener.clone();
+                    let confirm = self.confirm.clone();
+                    let jobs = &jobs;
+                    scope.spawn(move || {
+                        let mut wiper = Self {
+                            listener,
+                            confirm,
+                            ..Self::new(config)
+                        };
+                        let mut report = Report::default();
+                        let next = || jobs.lock().unwrap().recv().ok();
+                        while let Some(job) = next() {
+                            wiper.run(job, &mut report);
+                        }
+                        report
+                    })
+                })
+                .collect();
+            walk.queue = Some(queue);
+            self.walk(&mut walk, &mut report);
+            walk.queue = None;
+            for worker in workers {
+                report.merge(worker.join().unwrap());
+            }
+        });
+        report
+    }
+
+    /// Overwrite the file with the block of `round` and sync it. With
+    /// `direct` aligned chunks go through a second descriptor opened for
+    /// direct I/O, the unaligned rest through the page cache.
+    fn wipe(&mut self, path: &Path, round: u32) -> Result<u64> {
+        let mut file = sys::at(path)?.open_write()?;
+        let file_size = sys::size(&mut file)?;
+        if file_size == 0 {
+            return Err(format!("file: {} size is zero", path.display()).into());
+        }
+        let (mut direct, align) = if self.config.direct {
+            let direct = sys::at(path)?
+                .open_direct()
+                .map_err(|err| format!("file: {} direct I/O failed: {}", path.display(), err))?;
+            (Some(direct), sys::DIRECT_ALIGN as u64)
+        } else {
+            (None, 1)
+        };
+        self.prepare_block(round, self.config.block_size as u64, file_size);
+        let block_size = self.block.len() as u64;
+        let mut last = 0;
+        for (start, end) in self.ranges(&file, file_size)? {
+            last = end;
+            let mut pos = start;
+            while pos < end {
+                let offset = (pos % block_size) as usize;
+                let mut n = (block_size - offset as u64).min(end - pos);
+                if pos % align != 0 {
+                    n = n.min(align - pos % align);
+                } else if n >= align {
+                    n -= n % align;
+                }
+                let out = match direct.as_mut() {
+                    Some(v) if pos % align == 0 && n % align == 0 => v,
+                    _ => &mut file,
+                };
+                out.seek(SeekFrom::Start(pos))?;
+                out.write_all(&self.block[offset..offset + n as usize])?;
+                pos += n;
+                self.emit(Event::Progress(path, round, pos, file_size));
+            }
+        }
+        // A trailing hole is skipped, the end is reported all the same.
+        if last < file_size {
+            self.emit(Event::Progress(path, round, file_size, file_size));
+        }
+        file.sync_all()?;
+        Ok(file_size)
+    }
+
+    /// Ranges of the file to overwrite, as start and end offsets. The block
+    /// is laid out from the start of the file, so that it repeats the same
+    /// whatever the ranges.
+    fn ranges(&self, file: &fs::File, size: u64) -> Result<Vec<(u64, u64)>> {
+        if self.config.holes == Holes::Fill || sys::is_block_device(&file.metadata()?.file_type()) {
+            return Ok(vec![(0, size)]);
+        }
+        Ok(sys::data_ranges(file, size)?)
+    }
+
+    fn hash(&mut self, path: &Path) -> Result<String> {
+        let mut file = sys::at(path)?.open_read()?;
+        let size = sys::size(&mut file)?;
+        let mut file = file.take(size);
+        let mut hasher = crypto::sha512::Sha512::new();
+        loop {
+            self.read_buf.clear();
+            let n = (&mut file)
+                .take(self.config.block_size as u64)
+                .read_to_end(&mut self.read_buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&self.read_buf);
+        }
+        Ok(crypto::to_hex(&hasher.finalize()))
+    }
+
+    /// Compare the file content with the block of the last round repeated,
+    /// bytes missing count as mismatches.
+    fn verify(&mut self, path: &Path, size: u64) -> Result<u64> {
+        let mut file = sys::at(path)?.open_read()?;
+        sys::drop_cache(&file);
+        let block_size = self.block.len() as u64;
+        let mut mismatches = 0;
+        for (start, end) in self.ranges(&file, size)? {
+            file.seek(SeekFrom::Start(start))?;
+            let mut pos = start;
+            while pos < end {
+                let offset = (pos % block_size) as usize;
+                let n = (block_size - offset as u64).min(end - pos);
+                self.read_buf.clear();
+                let n = (&mut file).take(n).read_to_end(&mut self.read_buf)?;
+                if n == 0 {
+                    break;
+                }
+                mismatches += self
+                    .read_buf
+                    .iter()
+                    .zip(&self.block[offset..])
+                    .filter(|(a, b)| a != b)
+                    .count() as u64;
+                pos += n as u64;
+            }
+            mismatches += end - pos;
+        }
+        Ok(mismatches)
+    }
+
+    /// Walk with an explicit stack of entries, so deep trees do not run
+    /// out of the call stack.
+    fn walk(&mut self, walk: &mut Walk, report: &mut Report) {
+        while let Some(entry) = walk.stack.pop() {
+            if let Err(err) = self.visit(&entry, walk, report) {
+                report.errors.push((entry.path, err));
+                self.done(entry.parent.as_ref(), false, report);
+            }
+        }
+        for (_, linked) in self.linked.drain() {
+            if self.config.hardlinks == Hardlinks::WipeOnce && linked.seen < linked.links {
+                let err = format!(
+                    "{}: {} of {} names found, the content is left to the others",
+                    linked.path.display(),
+                    linked.seen,
+                    linked.links,
+                );
+                report.errors.push((linked.path, err.into()));
+            }
+        }
+    }
+
+    /// Every entry is a pending child of its parent, it is done with here
+    /// or, for a file wiped by a worker, later by the worker.
+    fn visit(&mut self, entry: &Entry, walk: &mut Walk, report: &mut Report) -> Result<()> {
+        let path = entry.path.as_path();
+        let depth = entry.depth;
+        let parent = entry.parent.as_ref();
+        let metadata = sys::at(path)?.symlink_metadata()?;
+        let is_device = depth == 0 && sys::is_block_device(&metadata.file_type());
+        let absolute = walk.root.join(relative(path, walk));
+        let kind = if is_device {
+            Kind::Device
+        } else if metadata.is_dir() {
+            Kind::Dir
+        } else if metadata.is_file() {
+            Kind::File
+        } else {
+            Kind::Link
+        };
+        if (depth == 0 || metadata.is_dir()) && !is_device && sys::is_pseudo(&absolute) {
+            let err = format!("{}: is on a pseudo filesystem", path.display());
+            return Err(self.refuse(path, kind, err));
+        }
+        if self.protected.contains(&absolute) {
+            return Err(self.refuse(path, kind, format!("{}: is protected", path.display())));
+        }
+        if depth > 0 && self.config.one_file_system && sys::device(&metadata) != walk.dev {
+            self.skip(path, parent, report);
+            return Ok(());
+        }
+        let is_included = depth == 0 || self.is_included(path, walk);
+        if depth > 0 && (self.is_excluded(path, walk) || !is_included && !metadata.is_dir()) {
+            self.skip(path, parent, report);
+            return Ok(());
+        }
+        if !metadata.is_dir() && !is_device && !self.is_selected(&metadata) {
+            self.skip(path, parent, report);
+            return Ok(());
+        }
+        if metadata.file_type().is_symlink() {
+            return self.symlink(entry, walk, report);
+        }
+        if metadata.is_dir() {
+            if depth > 0 && !self.config.recursive {
+                self.done(parent, false, report);
+                return Ok(());
+            }
+            if sys::file_id(&metadata).is_some_and(|v| !walk.visited.insert(v)) {
+                let err = format!("{}: directory loop", path.display());
+                return Err(self.refuse(path, Kind::Dir, err));
+            }
+            let entries = sys::at(path)?.read_dir()?;
+            // `.` and `..` have no name to rename and may not outlive their
+            // children, their canonical path is used for both.
+            let node_path = match path.file_name() {
+                Some(_) => path,
+                None => walk.root,
+            };
+            let node = Node::new(node_path, Kind::Dir, parent);
+            if !is_included {
+                node.kept.store(true, Ordering::Relaxed);
+            }
+            let children: Vec<_> = entries
+                .into_iter()
+                .map(|name| Entry {
+                    path: node_path.join(name),
+                    depth: depth + 1,
+                    parent: Some(node.clone()),
+                })
+                .collect();
+            node.pending.fetch_add(children.len(), Ordering::AcqRel);
+            walk.stack.extend(children.into_iter().rev());
+            self.done(Some(&node), true, report);
+            return Ok(());
+        }
+        let file_type = metadata.file_type();
+        if !file_type.is_file() && !is_device {
+            let is_gone = self.unlink(path, report)?;
+            self.done(parent, is_gone, report);
+            return Ok(());
+        }
+        // Names already removed no longer count, the inode is remembered
+        // with the number of names it had when first found.
+        let id = sys::file_id(&metadata);
+        let links = sys::links(&metadata);
+        let is_linked = id.is_some_and(|v| self.linked.contains_key(&v));
+        if !is_device && (is_linked || links > 1) {
+            let (seen, links) = match id {
+                Some(id) => {
+                    let linked = self.linked.entry(id).or_insert_with(|| Linked {
+                        path: path.to_path_buf(),
+                        seen: 0,
+                        links,
+                    });
+                    linked.seen += 1;
+                    (linked.seen, linked.links)
+                }
+                None => (1, links),
+            };
+            let unlink_only = match self.config.hardlinks {
+                Hardlinks::WipeOnce => seen < links,
+                Hardlinks::WipeShared => seen > 1,
+                Hardlinks::Skip => {
+                    self.skip(path, parent, report);
+                    return Ok(());
+                }
+                Hardlinks::UnlinkOnly => true,
+            };
+            if unlink_only {
+                let is_gone = self.unlink(path, report)?;
+                self.done(parent, is_gone, report);
+                return Ok(());
+            }
+        }
+        if !self.confirm(Event::Wipe(path), report) {
+            self.done(parent, false, report);
+            return Ok(());
+        }
+        let job = Entry {
+            path: path.to_path_buf(),
+            depth,
+            parent: parent.cloned(),
+        };
+        match &walk.queue {
+            Some(queue) => queue.send(job)?,
+            None => self.run(job, report),
+        }
+        Ok(())
+    }
+
+    fn is_included(&self, path: &Path, walk: &Walk) -> bool {
+        let include = &self.config.include;
+        include.is_empty()
+            || relative(path, walk)
+                .ancestors()
+                .any(|v| include.iter().any(|glob| glob.matches(v)))
+    }
+
+    /// Whether a file, link or special file passes the age, size and owner
+    /// filters, by its own metadata.
+    fn is_selected(&self, metadata: &fs::Metadata) -> bool {
+        let config = &self.config;
+        let size = metadata.len();
+        if config.min_size.is_some_and(|v| size < v) || config.max_size.is_some_and(|v| size > v) {
+            return false;
+        }
+        if let Some(age) = config.older_than {
+            match metadata.modified().ok().and_then(|v| v.elapsed().ok()) {
+                Some(v) if v >= age => {}
+                _ => return false,
+            }
+        }
+        config.owner.is_none() || sys::owner(metadata) == config.owner
+    }
+
+    /// Record a path the walk will not touch, the error goes to the report.
+    fn refuse<E: Into<Error>>(&self, path: &Path, kind: Kind, err: E) -> Error {
+        let err = err.into();
+        self.finish(Record::new(path, kind), &Err::<(), _>(&err));
+        err
+    }
+
+    /// Leave a path found by the walk in place.
+    fn skip(&mut self, path: &Path, parent: Option<&Arc<Node>>, report: &mut Report) {
+        self.emit(Event::Skip(path));
+        report.skipped += 1;
+        self.done(parent, false, report);
+    }
+
+    fn is_excluded(&self, path: &Path, walk: &Walk) -> bool {
+        let path = relative(path, walk);
+        self.config.exclude.iter().any(|glob| glob.matches(path))
+    }
+
+    fn symlink(&mut self, entry: &Entry, walk: &mut Walk, report: &mut Report) -> Result<()> {
+        let path = entry.path.as_path();
+        let parent = entry.parent.as_ref();
+        match self.config.symlinks {
+            Symlinks::Skip => self.skip(path, parent, report),
+            Symlinks::Unlink => {
+                let is_gone = self.unlink(path, report)?;
+                self.done(parent, is_gone, report);
+            }
+            Symlinks::Follow => {
+                // Inside the tree the target may be wiped already, then
+                // there is nothing left to follow.
+                let target = match path.canonicalize() {
+                    Err(err) if entry.depth > 0 && err.kind() == io::ErrorKind::NotFound => None,
+                    Err(err) => return Err(self.refuse(path, Kind::Link, err)),
+                    Ok(v) => Some(v),
+                };
+                if let Some(target) = &target {
+                    if !target.starts_with(walk.root) {
+                        let err = format!(
+                            "symlink: {} points outside of {}",
+                            path.display(),
+                            walk.root.display(),
+                        );
+                        return Err(self.refuse(path, Kind::Link, err));
+                    }
+                }
+                let target = match target {
+                    Some(v) if entry.depth == 0 => v,
+                    _ => {
+                        let is_gone = self.unlink(path, report)?;
+                        self.done(parent, is_gone, report);
+                        return Ok(());
+                    }
+                };
+                let node = Node::new(path, Kind::Link, parent);
+                node.pending.fetch_add(1, Ordering::AcqRel);
+                walk.stack.push(Entry {
+                    path: target,
+                    depth: entry.depth,
+                    parent: Some(node.clone()),
+                });
+                self.done(Some(&node), true, report);
+            }
+        }
+        Ok(())
+    }
+
+    /// Wipe a file found by the walk and tell its parent.
+    fn run(&mut self, job: Entry, report: &mut Report) {
+        let is_gone = match self.wipe_checked(&job.path) {
+            Ok((size, is_verified)) => {
+                report.bytes += size;
+                report.files += 1;
+                if is_verified {
+                    report.verified += 1;
+                }
+                true
+            }
+            Err(err) => {
+                report.errors.push((job.path, err));
+                false
+            }
+        };
+        self.done(job.parent.as_ref(), is_gone, report);
+    }
+
+    /// Count a child of `node` as done with. After the last one the node is
+    /// removed, if all children are gone, and its own parent is told.
+    fn done(&mut self, node: Option<&Arc<Node>>, mut is_gone: bool, report: &mut Report) {
+        let mut node = node.cloned();
+        while let Some(current) = node {
+            if !is_gone {
+                current.kept.store(true, Ordering::Relaxed);
+            }
+            if current.pending.fetch_sub(1, Ordering::AcqRel) != 1 {
+                return;
+            }
+            let res = if current.kept.load(Ordering::Relaxed) || self.config.keep {
+                Ok(false)
+            } else if current.kind == Kind::Dir {
+                self.remove_dir(&current.path, report)
+            } else {
+                self.unlink(&current.path, report)
+            };
+            is_gone = res.unwrap_or_else(|err| {
+                report.errors.push((current.path.clone(), err));
+                false
+            });
+            node = current.parent.clone();
+        }
+    }
+
+    fn remove_dir(&mut self, path: &Path, report: &mut Report) -> Result<bool> {
+        if !self.confirm(Event::RemoveDir(path), report) {
+            return Ok(false);
+        }
+        self.emit(Event::RemoveDir(path));
+        let mut record = Record::new(path, Kind::Dir);
+        let res = if self.config.dry_run {
+            Ok(())
+        } else {
+            self.rename(path).and_then(|v| {
+                sys::at(&v)?.remove_dir()?;
+                record.renamed = Some(v);
+                Ok(())
+            })
+        };
+        record.removed = res.is_ok() && !self.config.dry_run;
+        self.finish(record, &res);
+        res?;
+        report.dirs += 1;
+        Ok(true)
+    }
+
+    /// Remove a link, a special file or a name of a file with others,
+    /// without touching anything else.
+    fn unlink(&mut self, path: &Path, report: &mut Report) -> Result<bool> {
+        if self.config.keep {
+            return Ok(false);
+        }
+        if !self.confirm(Event::Unlink(path), report) {
+            return Ok(false);
+        }
+        self.emit(Event::Unlink(path));
+        let mut record = Record::new(path, Kind::Link);
+        let res = if self.config.dry_run {
+            Ok(())
+        } else {
+            sys::at(path).and_then(|v| v.remove_file())
+        };
+        record.removed = res.is_ok() && !self.config.dry_run;
+        self.finish(record, &res);
+        res?;
+        report.links += 1;
+        Ok(true)
+    }
+
+    /// Ask the confirm callback, a declined path is reported as skipped.
+    fn confirm(&self, event: Event, report: &mut Report) -> bool {
+        let path = match event {
+            Event::Wipe(v) | Event::Unlink(v) | Event::RemoveDir(v) => v,
+            _ => return true,
+        };
+        match &self.confirm {
+            Some(f) if !f(event) => {
+                self.emit(Event::Skip(path));
+                report.skipped += 1;
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Names found by the walk of a file with more than one.
+struct Linked {
+    /// The first name found.
+    path: PathBuf,
+    seen: u64,
+    links: u64,
+}
+
+/// State of a single [`Wiper::wipe_tree`] walk.
+struct Walk<'a> {
+    stack: Vec<Entry>,
+    /// Device and inode of directories walked into, to find loops.
+    visited: HashSet<(u64, u64)>,
+    /// Path given to [`Wiper::wipe_tree`], filters match relative to it.
+    top: &'a Path,
+    root: &'a Path,
+    /// Device of the top, the walk stays on it with `one_file_system`.
+    dev: Option<u64>,
+    /// Files go to the workers, if any.
+    queue: Option<mpsc::Sender<Entry>>,
+}
+
+/// Path found by the walk, with the directory or link waiting for it.
+struct Entry {
+    path: PathBuf,
+    depth: u32,
+    parent: Option<Arc<Node>>,
+}
+
+/// Directory, or followed symlink, removed after all its children.
+struct Node {
+    path: PathBuf,
+    kind: Kind,
+    parent: Option<Arc<Node>>,
+    /// Children not done with yet, plus one held by the walk itself.
+    pending: AtomicUsize,
+    /// Some child is left in place.
+    kept: AtomicBool,
+}
+
+impl Node {
+    fn new(path: &Path, kind: Kind, parent: Option<&Arc<Node>>) -> Arc<Self> {
+        Arc::new(Self {
+            path: path.to_path_buf(),
+            kind,
+            parent: parent.cloned(),
+            pending: AtomicUsize::new(1),
+            kept: AtomicBool::new(false),
+        })
+    }
+}
+
+impl Drop for Node {
+    /// Drop the chain of parents in a loop, it can be as long as the tree
+    /// is deep.
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(node) = parent {
+            parent = match Arc::try_unwrap(node) {
+                Ok(mut v) => v.parent.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+/// Path inside the tree, followed symlinks lead to canonical paths.
+fn relative<'a>(path: &'a Path, walk: &Walk) -> &'a Path {
+    path.strip_prefix(walk.top)
+        .or_else(|_| path.strip_prefix(walk.root))
+        .unwrap_or(path)
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+fn is_no_space(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded
+    )
+}
+
+/// Wipe a single file with the default config.
+pub fn wipe_file<P: AsRef<Path>>(path: P) -> Result<u64> {
+    Wiper::default().wipe_file(path)
+}
+
+/// Wipe a file or a directory tree with the given config.
+pub fn wipe_tree<P: AsRef<Path>>(path: P, config: &WipeConfig) -> Report {
+    config.clone().build().wipe_tree(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{LoopDevice, Mount, TempDir};
+
+    const IMAGE_SIZE: usize = 1 << 20;
+
+    fn image(dir: &TempDir) -> PathBuf {
+        let path = dir.path().join("image");
+        fs::write(&path, vec![0x55; IMAGE_SIZE]).unwrap();
+        path
+    }
+
+    /// Workers renaming one-byte names in the same directories run out of
+    /// free names at once, none may replace another.
+    #[test]
+    fn jobs_rename_without_clobbering() {
+        for _ in 0..3 {
+            let dir = TempDir::new();
+            let tree = dir.path().join("tree");
+            for d in 0..20 {
+                let sub = tree.join(d.to_string());
+                fs::create_dir_all(&sub).unwrap();
+                for c in b'a'..b'u' {
+                    fs::write(sub.join((c as char).to_string()), [c]).unwrap();
+                }
+            }
+            let report = WipeConfig::new()
+                .recursive(true)
+                .jobs(8)
+                .build()
+                .wipe_tree(&tree);
+            assert!(report.is_ok(), "{:?}", report.errors);
+            assert_eq!((report.files, report.dirs), (400, 21));
+            assert!(!tree.exists());
+        }
+    }
+
+    /// File of 1 MiB with 4 KiB of data in the middle, the rest is a hole.
+    fn sparse(dir: &TempDir) -> PathBuf {
+        let path = dir.path().join("sparse");
+        let mut file = fs::File::create(&path).unwrap();
+        file.set_len(IMAGE_SIZE as u64).unwrap();
+        file.seek(SeekFrom::Start(IMAGE_SIZE as u64 / 2)).unwrap();
+        file.write_all(&[0x55; 4096]).unwrap();
+        path
+    }
+
+    #[test]
+    fn holes_are_skipped() {
+        let dir = TempDir::new();
+        let path = sparse(&dir);
+        let data = IMAGE_SIZE / 2..IMAGE_SIZE / 2 + 4096;
+        let mut wiper = WipeConfig::new()
+            .patterns(vec![Pattern::One])
+            .holes(Holes::Skip)
+            .verify(Verify::Last)
+            .keep(true)
+            .build();
+        assert_eq!(wiper.wipe_file(&path).unwrap(), IMAGE_SIZE as u64);
+        let content = fs::read(&path).unwrap();
+        assert!(content[data.clone()].iter().all(|&v| v == 0xff));
+        let ranges = sys::data_ranges(&fs::File::open(&path).unwrap(), IMAGE_SIZE as u64).unwrap();
+        if ranges == [(0, IMAGE_SIZE as u64)] {
+            eprintln!("no holes on this filesystem, skipped");
+            return;
+        }
+        assert_eq!(ranges, [(data.start as u64, data.end as u64)]);
+        assert!(content[..data.start].iter().all(|&v| v == 0));
+        assert!(content[data.end..].iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn holes_are_filled() {
+        let dir = TempDir::new();
+        let path = sparse(&dir);
+        let mut wiper = WipeConfig::new()
+            .patterns(vec![Pattern::One])
+            .keep(true)
+            .build();
+        assert_eq!(wiper.wipe_file(&path).unwrap(), IMAGE_SIZE as u64);
+        assert!(fs::read(&path).unwrap().iter().all(|&v| v == 0xff));
+        let ranges = sys::data_ranges(&fs::File::open(&path).unwrap(), IMAGE_SIZE as u64).unwrap();
+        assert_eq!(ranges, [(0, IMAGE_SIZE as u64)]);
+    }
+
+    #[test]
+    fn block_device_is_wiped_in_place() {
+        let dir = TempDir::new();
+        let image = image(&dir);
+        let device = match LoopDevice::attach(&image) {
+            Some(v) => v,
+            None => return,
+        };
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = records.clone();
+        let mut wiper = WipeConfig::new()
+            .patterns(vec![Pattern::One])
+            .build()
+            .with_listener(move |event| {
+                if let Event::Record(v) = event {
+                    sink.lock().unwrap().push(v.clone());
+                }
+            });
+        let report = wiper.wipe_tree(device.path());
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.bytes), (1, IMAGE_SIZE as u64));
+        let record = records.lock().unwrap().pop().unwrap();
+        assert_eq!(record.kind, Kind::Device);
+        assert_eq!(record.size, IMAGE_SIZE as u64);
+        assert!(record.renamed.is_none() && !record.removed);
+        assert!(device.path().exists());
+        drop(device);
+        assert!(fs::read(&image).unwrap().iter().all(|&v| v == 0xff));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn block_device_in_tree_is_unlinked() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = TempDir::new();
+        let image = image(&dir);
+        let device = match LoopDevice::attach(&image) {
+            Some(v) => v,
+            None => return,
+        };
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        let node = CString::new(tree.join("node").as_os_str().as_bytes()).unwrap();
+        let rdev = fs::metadata(device.path()).unwrap().rdev();
+        assert_eq!(
+            unsafe { libc::mknod(node.as_ptr(), libc::S_IFBLK | 0o600, rdev) },
+            0
+        );
+        let report = WipeConfig::new().recursive(true).build().wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links, report.dirs), (0, 1, 1));
+        assert!(!tree.exists());
+        drop(device);
+        assert!(fs::read(&image).unwrap().iter().all(|&v| v == 0x55));
+    }
+
+    #[test]
+    fn dot_dot_top_is_removed() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("sub")).unwrap();
+        fs::write(tree.join("sub").join("f"), "f").unwrap();
+        let report = WipeConfig::new()
+            .recursive(true)
+            .build()
+            .wipe_tree(tree.join("sub").join(".."));
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.dirs), (1, 2));
+        assert!(!tree.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn top_link_is_unlinked() {
+        let dir = TempDir::new();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        fs::write(&target, "target").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let report = WipeConfig::new().build().wipe_tree(&link);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links), (0, 1));
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "target");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_stays_in_tree() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(tree.join("sub")).unwrap();
+        fs::write(tree.join("f"), "f").unwrap();
+        fs::write(&outside, "outside").unwrap();
+        symlink("f", tree.join("l")).unwrap();
+        symlink("sub", tree.join("link")).unwrap();
+        symlink(&outside, tree.join("out")).unwrap();
+        let report = WipeConfig::new()
+            .recursive(true)
+            .symlinks(Symlinks::Follow)
+            .build()
+            .wipe_tree(&tree);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("points outside"));
+        assert_eq!((report.files, report.links), (1, 2));
+        assert_eq!(fs::read_to_string(&outside).unwrap(), "outside");
+        let names: Vec<_> = fs::read_dir(&tree)
+            .unwrap()
+            .map(|v| v.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["out"]);
+    }
+
+    #[test]
+    fn progress_reaches_the_end_once_per_round() {
+        let dir = TempDir::new();
+        let path = image(&dir);
+        let ends = Arc::new(Mutex::new(Vec::new()));
+        let events = ends.clone();
+        let mut wiper = WipeConfig::new()
+            .patterns(vec![Pattern::One, Pattern::Zero])
+            .keep(true)
+            .build()
+            .with_listener(move |event| {
+                if let Event::Progress(_, round, pos, size) = event {
+                    if pos == size {
+                        events.lock().unwrap().push(round);
+                    }
+                }
+            });
+        wiper.wipe_file(&path).unwrap();
+        assert_eq!(*ends.lock().unwrap(), [0, 1]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn filters_apply_to_links_and_special_files() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        fs::write(tree.join("big"), [1; 4096]).unwrap();
+        fs::write(tree.join("small"), "s").unwrap();
+        std::os::unix::fs::symlink("big", tree.join("link")).unwrap();
+        let fifo = CString::new(tree.join("fifo").as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        let report = WipeConfig::new()
+            .recursive(true)
+            .min_size(100)
+            .build()
+            .wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links, report.skipped), (1, 0, 3));
+        let mut names: Vec<_> = fs::read_dir(&tree)
+            .unwrap()
+            .map(|v| v.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["fifo", "link", "small"]);
+
+        let old = SystemTime::now() - Duration::from_secs(7200);
+        let small = fs::File::options()
+            .write(true)
+            .open(tree.join("small"))
+            .unwrap();
+        small.set_times(FileTimes::new().set_modified(old)).unwrap();
+        let report = WipeConfig::new()
+            .recursive(true)
+            .older_than(Duration::from_secs(3600))
+            .build()
+            .wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links, report.skipped), (1, 0, 2));
+        let mut names: Vec<_> = fs::read_dir(&tree)
+            .unwrap()
+            .map(|v| v.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["fifo", "link"]);
+    }
+
+    #[test]
+    fn protected_paths_are_kept() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("sub").join("keep")).unwrap();
+        fs::write(tree.join("sub").join("keep").join("f"), "f").unwrap();
+        fs::write(tree.join("sub").join("g"), "g").unwrap();
+        let config = WipeConfig::new()
+            .recursive(true)
+            .protected(vec![tree.join("sub").join("keep"), tree.join("top")]);
+
+        fs::write(tree.join("top"), "top").unwrap();
+        let report = config.clone().build().wipe_tree(tree.join("top"));
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("is protected"));
+        assert_eq!(fs::read_to_string(tree.join("top")).unwrap(), "top");
+        fs::remove_file(tree.join("top")).unwrap();
+
+        // Relative paths and `..` are resolved before comparing.
+        let report = config
+            .clone()
+            .build()
+            .wipe_tree(tree.join("sub").join("..").join("sub").join("keep"));
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("is protected"));
+
+        let report = config.build().wipe_tree(&tree);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("is protected"));
+        assert_eq!(report.files, 1);
+        assert!(!tree.join("sub").join("g").exists());
+        assert!(tree.join("sub").join("keep").join("f").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn followed_link_to_protected_path_is_kept() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("keep")).unwrap();
+        fs::write(tree.join("keep").join("f"), "f").unwrap();
+        std::os::unix::fs::symlink("keep", tree.join("link")).unwrap();
+        let report = WipeConfig::new()
+            .recursive(true)
+            .symlinks(Symlinks::Follow)
+            .protected(vec![tree.join("keep")])
+            .build()
+            .wipe_tree(tree.join("link"));
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("is protected"));
+        assert!(fs::symlink_metadata(tree.join("link")).is_ok());
+        assert!(tree.join("keep").join("f").exists());
+    }
+
+    #[test]
+    fn protected_paths_without_preserve_root() {
+        let dir = TempDir::new();
+        let keep = dir.path().join("keep");
+        fs::write(&keep, "keep").unwrap();
+        let config = WipeConfig::new().preserve_root(false);
+        assert!(!config.protected_paths().contains(&PathBuf::from("/")));
+        let report = config
+            .clone()
+            .protected(vec![keep.clone()])
+            .build()
+            .wipe_tree(&keep);
+        assert_eq!(report.errors.len(), 1);
+        assert!(keep.exists());
+        let report = config.build().wipe_tree(&keep);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert!(!keep.exists());
+        assert!(WipeConfig::new()
+            .protected_paths()
+            .contains(&PathBuf::from("/")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refused_targets_are_recorded() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(tree.join("keep")).unwrap();
+        fs::write(&outside, "outside").unwrap();
+        std::os::unix::fs::symlink(&outside, tree.join("out")).unwrap();
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let events = records.clone();
+        let report = WipeConfig::new()
+            .recursive(true)
+            .symlinks(Symlinks::Follow)
+            .protected(vec![tree.join("keep")])
+            .build()
+            .with_listener(move |event| {
+                if let Event::Record(record) = event {
+                    events.lock().unwrap().push(record.clone());
+                }
+            })
+            .wipe_tree(&tree);
+        assert_eq!(report.errors.len(), 2);
+        let mut records = records.lock().unwrap().clone();
+        records.sort_by(|a, b| a.path.cmp(&b.path));
+        let records: Vec<_> = records
+            .iter()
+            .map(|v| (v.path.clone(), v.kind, v.removed, v.error.is_some()))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (tree.join("keep"), Kind::Dir, false, true),
+                (tree.join("out"), Kind::Link, false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn direct_keeps_period_over_unaligned_size() {
+        let dir = TempDir::new();
+        let path = dir.path().join("f");
+        let size = 5 * sys::DIRECT_ALIGN + 1234;
+        fs::write(&path, vec![0; size]).unwrap();
+        match sys::at(&path).unwrap().open_direct() {
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) => {
+                eprintln!("no direct I/O on this filesystem, skipped");
+                return;
+            }
+            res => drop(res.unwrap()),
+        }
+        let pattern = [0x92, 0x49, 0x24];
+        let mut wiper = WipeConfig::new()
+            .patterns(vec![Pattern::Fixed(pattern.to_vec())])
+            .block_size(2 * sys::DIRECT_ALIGN)
+            .direct(true)
+            .verify(Verify::Last)
+            .keep(true)
+            .build();
+        assert_eq!(wiper.wipe_file(&path).unwrap(), size as u64);
+        let content = fs::read(&path).unwrap();
+        assert_eq!(content.len(), size);
+        assert!(content
+            .iter()
+            .enumerate()
+            .all(|(i, &v)| v == pattern[i % 3]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn other_filesystems_are_skipped() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("mnt")).unwrap();
+        fs::write(tree.join("f"), "f").unwrap();
+        let _mount = match Mount::new("tmpfs", "size=1m", &tree.join("mnt")) {
+            Some(v) => v,
+            None => return,
+        };
+        fs::write(tree.join("mnt").join("g"), "g").unwrap();
+        let report = WipeConfig::new()
+            .recursive(true)
+            .one_file_system(true)
+            .build()
+            .wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.dirs, report.skipped), (1, 0, 1));
+        assert!(!tree.join("f").exists());
+        assert_eq!(fs::read_to_string(tree.join("mnt").join("g")).unwrap(), "g");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pseudo_filesystems_are_refused() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        let pts = tree.join("pts");
+        fs::create_dir_all(&pts).unwrap();
+        fs::write(tree.join("f"), "f").unwrap();
+        // A private devpts instance, nothing in it is worth wiping.
+        let _mount = match Mount::new("devpts", "newinstance", &pts) {
+            Some(v) => v,
+            None => return,
+        };
+        let report = WipeConfig::new().recursive(true).build().wipe_tree(&pts);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("pseudo filesystem"));
+        let report = WipeConfig::new().recursive(true).build().wipe_tree(&tree);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].1.to_string().contains("pseudo filesystem"));
+        assert_eq!((report.files, report.dirs), (1, 0));
+        assert!(!tree.join("f").exists());
+        assert!(pts.join("ptmx").exists());
+    }
+
+    /// Tree with three names of one file, `outside` is a fourth name.
+    #[cfg(unix)]
+    fn linked_tree(dir: &TempDir) -> (PathBuf, PathBuf) {
+        let tree = dir.path().join("tree");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(tree.join("sub")).unwrap();
+        fs::write(tree.join("a"), "content").unwrap();
+        fs::hard_link(tree.join("a"), tree.join("b")).unwrap();
+        fs::hard_link(tree.join("a"), tree.join("sub").join("c")).unwrap();
+        (tree, outside)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_in_tree_are_wiped_once() {
+        let dir = TempDir::new();
+        let (tree, _) = linked_tree(&dir);
+        let config = WipeConfig::new()
+            .recursive(true)
+            .patterns(vec![Pattern::One]);
+        let report = config.clone().keep(true).build().wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links), (1, 0));
+        assert_eq!(fs::read(tree.join("b")).unwrap(), [0xff; 7]);
+        let report = config.build().wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links, report.dirs), (1, 2, 2));
+        assert!(!tree.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_out_of_tree_are_left_the_content() {
+        let dir = TempDir::new();
+        let (tree, outside) = linked_tree(&dir);
+        fs::hard_link(tree.join("a"), &outside).unwrap();
+        let report = WipeConfig::new().recursive(true).build().wipe_tree(&tree);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0]
+            .1
+            .to_string()
+            .contains("3 of 4 names found"));
+        assert_eq!((report.files, report.links, report.dirs), (0, 3, 2));
+        assert!(!tree.exists());
+        assert_eq!(fs::read_to_string(&outside).unwrap(), "content");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_policies() {
+        let dir = TempDir::new();
+        let (tree, outside) = linked_tree(&dir);
+        fs::hard_link(tree.join("a"), &outside).unwrap();
+        let config = WipeConfig::new()
+            .recursive(true)
+            .patterns(vec![Pattern::One]);
+
+        let report = config
+            .clone()
+            .hardlinks(Hardlinks::Skip)
+            .build()
+            .wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links, report.skipped), (0, 0, 3));
+        assert!(tree.join("sub").join("c").exists());
+
+        let report = config
+            .clone()
+            .hardlinks(Hardlinks::UnlinkOnly)
+            .build()
+            .wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links), (0, 3));
+        assert!(!tree.exists());
+        assert_eq!(fs::read_to_string(&outside).unwrap(), "content");
+
+        let (tree, _) = linked_tree(&dir);
+        for name in [tree.join("a"), tree.join("b"), tree.join("sub").join("c")] {
+            fs::remove_file(&name).unwrap();
+            fs::hard_link(&outside, &name).unwrap();
+        }
+        let report = config
+            .hardlinks(Hardlinks::WipeShared)
+            .build()
+            .wipe_tree(&tree);
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!((report.files, report.links), (1, 2));
+        assert!(!tree.exists());
+        assert_eq!(fs::read(&outside).unwrap(), [0xff; 7]);
+    }
+
+    /// Directories below `top` nested deeper than a path can be long, with
+    /// a file at the bottom. Returns the number of directories.
+    #[cfg(target_os = "linux")]
+    fn deep_tree(top: &Path) -> usize {
+        use std::os::unix::io::{AsRawFd, FromRawFd};
+
+        const DEPTH: usize = 64;
+
+        let name = std::ffi::CString::new("d".repeat(100)).unwrap();
+        let mut dir = fs::File::open(top).unwrap();
+        for _ in 0..DEPTH {
+            let fd = dir.as_raw_fd();
+            assert_eq!(unsafe { libc::mkdirat(fd, name.as_ptr(), 0o700) }, 0);
+            let fd = unsafe { libc::openat(fd, name.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+            assert!(fd >= 0);
+            dir = unsafe { fs::File::from_raw_fd(fd) };
+        }
+        let file = std::ffi::CString::new("f").unwrap();
+        let flags = libc::O_WRONLY | libc::O_CREAT;
+        let fd = unsafe { libc::openat(dir.as_raw_fd(), file.as_ptr(), flags, 0o600) };
+        assert!(fd >= 0);
+        io::Write::write_all(&mut unsafe { fs::File::from_raw_fd(fd) }, b"deep").unwrap();
+        DEPTH
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn deep_trees_are_wiped() {
+        for jobs in [1, 4] {
+            let dir = TempDir::new();
+            let tree = dir.path().join("tree");
+            fs::create_dir(&tree).unwrap();
+            let depth = deep_tree(&tree);
+            let report = WipeConfig::new()
+                .recursive(true)
+                .verify(Verify::Last)
+                .jobs(jobs)
+                .build()
+                .wipe_tree(&tree);
+            assert!(report.is_ok(), "{:?}", report.errors);
+            assert_eq!((report.files, report.verified), (1, 1));
+            assert_eq!(report.dirs, depth as u64 + 1);
+            assert!(!tree.exists());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn directory_loops_are_reported() {
+        let dir = TempDir::new();
+        let tree = dir.path().join("tree");
+        let target = tree.join("sub").join("loop");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(tree.join("f"), "f").unwrap();
+        let mount = match Mount::bind(&tree, &target) {
+            Some(v) => v,
+            None => return,
+        };
+        let report = WipeConfig::new().recursive(true).build().wipe_tree(&tree);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, target);
+        assert!(report.errors[0].1.to_string().contains("directory loop"));
+        assert_eq!((report.files, report.dirs), (1, 0));
+        drop(mount);
+        assert!(!tree.join("f").exists());
+        assert!(target.exists());
+    }
+}
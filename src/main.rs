@@ -1,31 +1,66 @@
 use std::collections::HashSet;
 use std::env;
-use std::error;
-use std::fs;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
 use std::process::exit;
-use std::result;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use rand::rngs::ThreadRng;
-use rand::seq::SliceRandom;
+use viper::cert::{self, Certificate, PublicKey, SigningKey};
+use viper::progress::Progress;
+use viper::{default, Error, Event, Glob, Pattern, Report, Result, Scheme, Verify, WipeConfig};
 
 const EXIT_SUCCESS: i32 = 0;
 const EXIT_USAGE: i32 = 2;
 
 mod flag {
-    pub const HELP: &'static str = "h";
-    pub const VERSION: &'static str = "V";
-    pub const VERBOSE: &'static str = "v";
-    pub const RECURSIVE: &'static str = "r";
-    pub const ZERO: &'static str = "z";
-    pub const NUM_ROUNDS: &'static str = "n";
-    pub const BLOCK_SIZE: &'static str = "b";
+    pub const HELP: &str = "h";
+    pub const VERSION: &str = "V";
+    pub const VERBOSE: &str = "v";
+    pub const RECURSIVE: &str = "r";
+    pub const ONE_FILE_SYSTEM: &str = "x";
+    pub const ONE_FILE_SYSTEM_LONG: &str = "one-file-system";
+    pub const ZERO: &str = "z";
+    pub const NUM_ROUNDS: &str = "n";
+    pub const BLOCK_SIZE: &str = "b";
+    pub const PATTERNS: &str = "p";
+    pub const JOBS: &str = "j";
+    pub const RENAMES: &str = "renames";
+    pub const TRUNCATE: &str = "truncate";
+    pub const TIMESTAMPS: &str = "timestamps";
+    pub const STRICT: &str = "strict";
+    pub const KEEP: &str = "keep";
+    pub const INCLUDE: &str = "include";
+    pub const EXCLUDE: &str = "exclude";
+    pub const EXCLUDE_FROM: &str = "exclude-from";
+    pub const OLDER_THAN: &str = "older-than";
+    pub const MIN_SIZE: &str = "min-size";
+    pub const MAX_SIZE: &str = "max-size";
+    pub const OWNER: &str = "owner";
+    pub const NO_PRESERVE_ROOT: &str = "no-preserve-root";
+    pub const PROTECT_FROM: &str = "protect-from";
+    pub const SCHEME: &str = "scheme";
+    pub const VERIFY: &str = "verify";
+    pub const SYMLINKS: &str = "symlinks";
+    pub const HARDLINKS: &str = "hardlinks";
+    pub const HOLES: &str = "holes";
+    pub const DIRECT: &str = "direct";
+    pub const DRY_RUN: &str = "dry-run";
+    pub const FORCE: &str = "f";
+    pub const FORCE_LONG: &str = "force";
+    pub const INTERACTIVE: &str = "i";
+    pub const FREE_SPACE: &str = "free-space";
+    pub const PROGRESS: &str = "progress";
+    pub const REPORT: &str = "report";
+    pub const CERT: &str = "cert";
+    pub const CERT_KEY: &str = "cert-key";
 }
 
-mod default {
-    pub const NUM_ROUNDS: i32 = 1;
-    pub const BLOCK_SIZE: i32 = 8;
+mod command {
+    pub const KEYGEN: &str = "keygen";
+    pub const VERIFY_CERT: &str = "verify-cert";
 }
 
 enum PrintDestination {
@@ -35,14 +70,59 @@ enum PrintDestination {
 
 fn print_usage(to: PrintDestination) {
     let usage = format!(
-        "{P} [-{h}|{V}] [-{v}{v}] [-{r}] [-{z}] [-{n} NUM] [-{b} NUM] FILES\n\n\
+        "{P} {keygen} KEY\n\
+         {P} {verify_cert} CERT PUBKEY\n\
+         {P} [-{h}|{V}] [-{v}{v}] [-{r}{x}] [-{z}] [-{f}|{i}] [-{n} NUM] [-{b} NUM] [-{p} LIST]\n\
+         \x20     [-{j} NUM] [--{renames} NUM] [--{scheme} NAME] [--{verify}[=WHEN]]\n\
+         \x20     [--{truncate}] [--{timestamps} MODE]\n\
+         \x20     [--{symlinks} POLICY] [--{hardlinks} POLICY] [--{holes} MODE] [--{direct}]\n\
+         \x20     [--{keep}[=zero]] [--{strict}] [--{dry_run}] [--{free_space} DIR] [--{progress}]\n\
+         \x20     [--{include} GLOB] [--{exclude} GLOB] [--{exclude_from} FILE]\n\
+         \x20     [--{older_than} AGE] [--{min_size} SIZE] [--{max_size} SIZE] [--{owner} UID]\n\
+         \x20     [--{no_preserve_root}] [--{protect_from} FILE]\n\
+         \x20     [--{report} FORMAT] [--{cert} FILE --{cert_key} KEY] FILES\n\n\
+         {keygen} * Create a signing key KEY and its public key KEY.pub\n\
+         {verify_cert} * Check that a certificate is signed with PUBKEY\n\n\
          [-{h}] * Print help and exit\n\
          [-{V}] * Print version and exit\n\
          [-{v}] * Tell what is going on\n\
          [-{r}] * Walk directories recursively\n\
+         [-{x}] * Stay on the filesystem of every file (--{one_file_system})\n\
          [-{z}] * First overwrite with zeroes\n\
+         [-{f}] * Do not ask for confirmation (--{force})\n\
+         [-{i}] * Ask before every file, directory and link\n\
          [-{n}] * Number of rounds to overwrite (default: {dn})\n\
-         [-{b}] * Maximum block size in MB (default: {db})",
+         [-{b}] * Maximum block size in MB (default: {db})\n\
+         [-{j}] * Number of files to wipe at once (default: {dj})\n\
+         [--{renames}] * Number of times to rename before removal (default: {dr})\n\
+         [--{truncate}] * Truncate to zero length before removal\n\
+         [--{timestamps}] * Reset times before removal (keep, epoch, random; default: keep)\n\
+         [--{keep}] * Overwrite only, leave everything in place, with zeroes last if =zero\n\
+         [--{strict}] * Fail on empty files instead of removing them\n\
+         [-{p}] * Comma separated patterns of rounds, overrides -{z} and -{n}\n\
+         \x20     (ascii, zero, one, random, 0xHEX)\n\
+         [--{scheme}] * Standard wipe scheme, overrides -{z} and -{n}\n\
+         \x20            ({schemes})\n\
+         [--{verify}] * Read back after the last or all rounds (last, all)\n\
+         [--{symlinks}] * Skip, unlink or follow inside the tree (default: unlink)\n\
+         [--{hardlinks}] * Wipe files with more names once, skip or only unlink them\n\
+         \x20             (wipe-once, wipe-shared, skip, unlink-only; default: wipe-once)\n\
+         [--{holes}] * Overwrite holes of sparse files or only data (fill, skip; default: fill)\n\
+         [--{direct}] * Write with direct I/O, past the page cache\n\
+         [--{include}] * Inside directories only wipe what matches, may repeat\n\
+         [--{exclude}] * Inside directories leave what matches in place, may repeat\n\
+         [--{exclude_from}] * Read exclude patterns from a file, one per line\n\
+         [--{older_than}] * Only wipe files modified before, in days or with s, m, h, d\n\
+         [--{min_size}] * Only wipe files of at least SIZE bytes or with K, M, G, T\n\
+         [--{max_size}] * Only wipe files of at most SIZE bytes or with K, M, G, T\n\
+         [--{owner}] * Only wipe files owned by UID\n\
+         [--{no_preserve_root}] * Allow wiping /, system directories and home\n\
+         [--{protect_from}] * Never wipe paths listed in a file, one per line\n\
+         [--{dry_run}] * Only print what would be wiped\n\
+         [--{free_space}] * Fill free space of the filesystem with files, wipe and remove them\n\
+         [--{progress}] * Show progress with throughput and ETA on stderr\n\
+         [--{report}] * Print a record of every target instead (json, jsonl)\n\
+         [--{cert}] * Write a certificate with hashes of the content, signed by --{cert_key}",
         P = PathBuf::from(env::args_os().next().unwrap())
             .file_name()
             .unwrap()
@@ -51,11 +131,53 @@ fn print_usage(to: PrintDestination) {
         V = flag::VERSION,
         v = flag::VERBOSE,
         r = flag::RECURSIVE,
+        x = flag::ONE_FILE_SYSTEM,
+        one_file_system = flag::ONE_FILE_SYSTEM_LONG,
         z = flag::ZERO,
+        f = flag::FORCE,
+        force = flag::FORCE_LONG,
+        i = flag::INTERACTIVE,
         n = flag::NUM_ROUNDS,
         b = flag::BLOCK_SIZE,
+        p = flag::PATTERNS,
+        j = flag::JOBS,
+        renames = flag::RENAMES,
+        truncate = flag::TRUNCATE,
+        timestamps = flag::TIMESTAMPS,
+        strict = flag::STRICT,
+        keep = flag::KEEP,
+        include = flag::INCLUDE,
+        exclude = flag::EXCLUDE,
+        exclude_from = flag::EXCLUDE_FROM,
+        older_than = flag::OLDER_THAN,
+        min_size = flag::MIN_SIZE,
+        max_size = flag::MAX_SIZE,
+        owner = flag::OWNER,
+        no_preserve_root = flag::NO_PRESERVE_ROOT,
+        protect_from = flag::PROTECT_FROM,
+        scheme = flag::SCHEME,
+        verify = flag::VERIFY,
+        symlinks = flag::SYMLINKS,
+        hardlinks = flag::HARDLINKS,
+        holes = flag::HOLES,
+        direct = flag::DIRECT,
+        dry_run = flag::DRY_RUN,
+        free_space = flag::FREE_SPACE,
+        progress = flag::PROGRESS,
+        report = flag::REPORT,
+        cert = flag::CERT,
+        cert_key = flag::CERT_KEY,
+        keygen = command::KEYGEN,
+        verify_cert = command::VERIFY_CERT,
+        schemes = Scheme::ALL
+            .iter()
+            .map(Scheme::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
         dn = default::NUM_ROUNDS,
-        db = default::BLOCK_SIZE,
+        db = default::BLOCK_SIZE >> 20,
+        dj = default::JOBS,
+        dr = default::RENAMES,
     );
     match to {
         PrintDestination::Stdout => println!("{}", usage),
@@ -63,16 +185,89 @@ fn print_usage(to: PrintDestination) {
     }
 }
 
-type Result<T> = result::Result<T, Box<dyn error::Error>>;
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    JsonLines,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::JsonLines),
+            _ => Err(format!("unknown report format: {}", s).into()),
+        }
+    }
+}
 
 #[derive(Default)]
 struct Opts {
     verbose: i32,
-    recursive: bool,
-    zero: bool,
-    num_rounds: i32,
-    block_size: i32,
+    dry_run: bool,
+    force: bool,
+    interactive: bool,
+    progress: bool,
+    format: Option<Format>,
+    scheme: Option<Scheme>,
+    cert: Option<PathBuf>,
+    cert_key: Option<PathBuf>,
+    config: WipeConfig,
     files: HashSet<PathBuf>,
+    free_space: HashSet<PathBuf>,
+}
+
+fn flag_name(name: &str) -> String {
+    if name.len() == 1 {
+        format!("-{}", name)
+    } else {
+        format!("--{}", name)
+    }
+}
+
+fn missing_arg(name: &str) -> ! {
+    eprintln!("missing value for: {}", flag_name(name));
+    exit(EXIT_USAGE);
+}
+
+/// Number with an optional unit suffix, multiplied by the unit.
+fn parse_unit(s: &str, units: &[(char, u64)], default: u64) -> Result<u64> {
+    let (number, unit) = match s.char_indices().last() {
+        Some((pos, c)) if c.is_ascii_alphabetic() => {
+            let unit = units
+                .iter()
+                .find(|(v, _)| v.eq_ignore_ascii_case(&c))
+                .ok_or_else(|| format!("unknown unit: {}", s))?;
+            (&s[..pos], unit.1)
+        }
+        _ => (s, default),
+    };
+    let number: u64 = number.parse()?;
+    number
+        .checked_mul(unit)
+        .ok_or_else(|| format!("too large: {}", s).into())
+}
+
+fn parse_size(s: &str) -> Result<u64> {
+    parse_unit(
+        s,
+        &[
+            ('k', 1 << 10),
+            ('m', 1 << 20),
+            ('g', 1 << 30),
+            ('t', 1 << 40),
+        ],
+        1,
+    )
+}
+
+fn parse_age(s: &str) -> Result<Duration> {
+    const DAY: u64 = 24 * 60 * 60;
+
+    let secs = parse_unit(s, &[('s', 1), ('m', 60), ('h', 60 * 60), ('d', DAY)], DAY)?;
+    Ok(Duration::from_secs(secs))
 }
 
 fn get_opts() -> Result<Opts> {
@@ -82,20 +277,16 @@ fn get_opts() -> Result<Opts> {
         exit(EXIT_USAGE);
     }
     let mut opts = Opts::default();
-    let missing_arg = |s| {
-        eprintln!("missing value for: -{}", s);
-        exit(EXIT_USAGE);
-    };
-    loop {
-        let arg = match argv.next() {
-            Some(v) => match v.into_string() {
-                Ok(s) => s,
-                Err(v) => {
-                    opts.files.insert(v.into());
-                    continue;
-                }
-            },
-            None => break,
+    let mut include: Vec<Glob> = Vec::new();
+    let mut exclude: Vec<Glob> = Vec::new();
+    let mut protected: Vec<PathBuf> = Vec::new();
+    while let Some(arg) = argv.next() {
+        let arg = match arg.into_string() {
+            Ok(s) => s,
+            Err(v) => {
+                opts.files.insert(v.into());
+                continue;
+            }
         };
         if !arg.starts_with('-') {
             if !arg.is_empty() {
@@ -103,8 +294,19 @@ fn get_opts() -> Result<Opts> {
             }
             continue;
         }
-        for c in arg.chars().skip(1) {
-            match c.to_string().as_str() {
+        let (names, mut inline): (Vec<String>, _) = match arg.strip_prefix("--") {
+            Some(s) => match s.split_once('=') {
+                Some((name, value)) => (vec![name.into()], Some(OsString::from(value))),
+                None => (vec![s.into()], None),
+            },
+            None => (arg.chars().skip(1).map(String::from).collect(), None),
+        };
+        for name in &names {
+            let mut value = || match inline.take().or_else(|| argv.next()) {
+                Some(v) => v,
+                None => missing_arg(name),
+            };
+            match name.as_str() {
                 flag::HELP => {
                     print_usage(PrintDestination::Stdout);
                     exit(EXIT_SUCCESS);
@@ -114,180 +316,395 @@ fn get_opts() -> Result<Opts> {
                     exit(EXIT_SUCCESS);
                 }
                 flag::VERBOSE => opts.verbose += 1,
-                flag::RECURSIVE => opts.recursive = true,
-                flag::ZERO => opts.zero = true,
-                flag::NUM_ROUNDS => match argv.next() {
-                    Some(s) => opts.num_rounds = s.to_str().unwrap().parse()?,
-                    None => missing_arg(flag::NUM_ROUNDS),
-                },
-                flag::BLOCK_SIZE => match argv.next() {
-                    Some(s) => opts.block_size = s.to_str().unwrap().parse()?,
-                    None => missing_arg(flag::BLOCK_SIZE),
-                },
-                _ => {}
+                flag::RECURSIVE => opts.config = opts.config.recursive(true),
+                flag::ONE_FILE_SYSTEM | flag::ONE_FILE_SYSTEM_LONG => {
+                    opts.config = opts.config.one_file_system(true)
+                }
+                flag::ZERO => opts.config = opts.config.zero(true),
+                flag::NUM_ROUNDS => {
+                    opts.config = opts.config.num_rounds(value().to_str().unwrap().parse()?)
+                }
+                flag::BLOCK_SIZE => {
+                    let mb: usize = value().to_str().unwrap().parse()?;
+                    opts.config = opts.config.block_size(mb << 20);
+                }
+                flag::JOBS => opts.config = opts.config.jobs(value().to_str().unwrap().parse()?),
+                flag::RENAMES => {
+                    opts.config = opts.config.renames(value().to_str().unwrap().parse()?)
+                }
+                flag::TRUNCATE => opts.config = opts.config.truncate(true),
+                flag::DIRECT => opts.config = opts.config.direct(true),
+                flag::TIMESTAMPS => {
+                    opts.config = opts.config.timestamps(value().to_str().unwrap().parse()?)
+                }
+                flag::KEEP => {
+                    match inline.take() {
+                        Some(v) if v == "zero" => opts.config = opts.config.zero_last(true),
+                        Some(v) => {
+                            return Err(format!("unknown keep mode: {}", v.to_string_lossy()).into())
+                        }
+                        None => {}
+                    }
+                    opts.config = opts.config.keep(true);
+                }
+                flag::INCLUDE => include.push(value().to_str().unwrap().parse()?),
+                flag::EXCLUDE => exclude.push(value().to_str().unwrap().parse()?),
+                flag::EXCLUDE_FROM => {
+                    for line in std::fs::read_to_string(value())?.lines() {
+                        let line = line.trim();
+                        if !line.is_empty() && !line.starts_with('#') {
+                            exclude.push(line.parse()?);
+                        }
+                    }
+                }
+                flag::OLDER_THAN => {
+                    opts.config = opts
+                        .config
+                        .older_than(parse_age(value().to_str().unwrap())?)
+                }
+                flag::MIN_SIZE => {
+                    opts.config = opts.config.min_size(parse_size(value().to_str().unwrap())?)
+                }
+                flag::MAX_SIZE => {
+                    opts.config = opts.config.max_size(parse_size(value().to_str().unwrap())?)
+                }
+                flag::OWNER => opts.config = opts.config.owner(value().to_str().unwrap().parse()?),
+                flag::NO_PRESERVE_ROOT => opts.config = opts.config.preserve_root(false),
+                flag::PROTECT_FROM => {
+                    for line in std::fs::read_to_string(value())?.lines() {
+                        let line = line.trim();
+                        if !line.is_empty() && !line.starts_with('#') {
+                            protected.push(line.into());
+                        }
+                    }
+                }
+                flag::STRICT => opts.config = opts.config.strict(true),
+                flag::PATTERNS => {
+                    let patterns = value()
+                        .to_str()
+                        .unwrap()
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<Vec<Pattern>>>()?;
+                    opts.scheme = None;
+                    opts.config = opts.config.patterns(patterns);
+                }
+                flag::SCHEME => {
+                    let scheme = value().to_str().unwrap().parse()?;
+                    opts.scheme = Some(scheme);
+                    opts.config = opts.config.scheme(scheme);
+                }
+                flag::VERIFY => {
+                    let verify = match inline.take() {
+                        Some(v) => v.to_str().unwrap().parse()?,
+                        None => Verify::Last,
+                    };
+                    opts.config = opts.config.verify(verify);
+                }
+                flag::SYMLINKS => {
+                    opts.config = opts.config.symlinks(value().to_str().unwrap().parse()?)
+                }
+                flag::HARDLINKS => {
+                    opts.config = opts.config.hardlinks(value().to_str().unwrap().parse()?)
+                }
+                flag::HOLES => opts.config = opts.config.holes(value().to_str().unwrap().parse()?),
+                flag::DRY_RUN => {
+                    opts.dry_run = true;
+                    opts.config = opts.config.dry_run(true);
+                }
+                flag::PROGRESS => opts.progress = true,
+                flag::REPORT => opts.format = Some(value().to_str().unwrap().parse()?),
+                flag::CERT => opts.cert = Some(value().into()),
+                flag::CERT_KEY => opts.cert_key = Some(value().into()),
+                flag::FREE_SPACE => {
+                    opts.free_space.insert(value().into());
+                }
+                flag::FORCE | flag::FORCE_LONG => {
+                    opts.force = true;
+                    opts.interactive = false;
+                }
+                flag::INTERACTIVE => {
+                    opts.interactive = true;
+                    opts.force = false;
+                }
+                _ => {
+                    eprintln!("unknown flag: {}", flag_name(name));
+                    exit(EXIT_USAGE);
+                }
             }
         }
     }
-    if opts.files.is_empty() {
+    if opts.files.is_empty() && opts.free_space.is_empty() {
         eprintln!("no files");
         exit(EXIT_USAGE);
     }
-    if opts.num_rounds < 1 {
-        opts.num_rounds = default::NUM_ROUNDS;
+    opts.config = opts
+        .config
+        .include(include)
+        .exclude(exclude)
+        .protected(protected);
+    if opts.interactive {
+        // prompts of workers would interleave
+        opts.config = opts.config.jobs(1);
     }
-    if opts.block_size < 1 {
-        opts.block_size = default::BLOCK_SIZE;
+    match (&opts.cert, &opts.cert_key) {
+        (Some(_), None) => missing_arg(flag::CERT_KEY),
+        (None, Some(_)) => missing_arg(flag::CERT),
+        (Some(_), Some(_)) if opts.dry_run => {
+            eprintln!(
+                "{} is not allowed with {}",
+                flag_name(flag::CERT),
+                flag_name(flag::DRY_RUN),
+            );
+            exit(EXIT_USAGE);
+        }
+        (Some(_), Some(_)) => opts.config = opts.config.hash(true),
+        (None, None) => {}
     }
-    opts.block_size *= 1 << 20;
     Ok(opts)
 }
 
-fn make_values() -> Vec<String> {
-    let mut res = Vec::with_capacity(62);
-    for a in 0..2 {
-        for b in 1..if a != 0 { 8 } else { 9 } {
-            for c in 0..4 {
-                let mut s = String::with_capacity(14);
-                s.push('8');
-                if a != 0 {
-                    s.push('#');
-                }
-                s.push_str("=".repeat(b).as_str());
-                s.push('D');
-                if c != 0 {
-                    s.push(' ');
-                    s.push_str("~".repeat(c).as_str());
-                }
-                res.push(s);
-            }
-        }
-    }
-    res.push("{()}".into());
-    res.push("({})".into());
-    res
+fn keygen(mut args: impl Iterator<Item = OsString>) -> Result<()> {
+    let path = PathBuf::from(args.next().ok_or("missing key file")?);
+    let mut public_path = path.clone().into_os_string();
+    public_path.push(".pub");
+    let key = SigningKey::generate();
+    key.save(&path)?;
+    key.public_key().save(&public_path)?;
+    println!("{}", key.public_key());
+    Ok(())
 }
 
-fn make_block(size: u64, params: &mut Params) -> Vec<u8> {
-    let mut res = Vec::with_capacity(size as usize);
-    let mut pos = 0;
-    while pos < size {
-        let value = params.values.choose(&mut params.rng).unwrap().as_bytes();
-        let value_len = value.len() as u64;
-        pos += value_len + 1;
-        if pos > size {
-            res.extend_from_slice(&value[..(value_len - (pos - size) - 1) as usize]);
-            break;
-        }
-        res.extend_from_slice(value);
-        res.push(b' ');
+fn verify_cert(mut args: impl Iterator<Item = OsString>) -> Result<()> {
+    let path = args.next().ok_or("missing certificate file")?;
+    let key = PublicKey::load(args.next().ok_or("missing public key file")?)?;
+    cert::verify(&std::fs::read_to_string(path)?, &key)?;
+    println!("good signature by {}", key);
+    Ok(())
+}
+
+fn ask(question: &str) -> bool {
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
     }
-    res
+    matches!(answer.trim(), "y" | "Y" | "yes")
 }
 
-fn wipe(path: &Path, round: i32, params: &mut Params) -> Result<()> {
-    let mut file = fs::OpenOptions::new().write(true).open(path)?;
-    let file_size = file.metadata()?.len();
-    if file_size == 0 {
-        return Err(format!("file: {} size is zero", path.display()).into());
+/// Walk all files on dry run to find out what is about to be wiped,
+/// free space counts with what is available now.
+fn scan(opts: &Opts) -> Report {
+    let mut wiper = opts.config.clone().dry_run(true).build();
+    let mut total = Report::default();
+    for file in &opts.files {
+        total.merge(wiper.wipe_tree(file));
     }
-    let block_size;
-    let tmp;
-    let block: &[u8] = if round == 0 {
-        block_size = params.opts.block_size as u64;
-        params.zero_block.as_ref().unwrap()
-    } else {
-        block_size = file_size.min(params.opts.block_size as u64);
-        tmp = make_block(block_size, params);
-        &tmp
-    };
-    let mut pos = 0;
-    while pos < file_size {
-        let mut value = block;
-        pos += block_size;
-        if pos > file_size {
-            value = &value[..(block_size - (pos - file_size)) as usize];
+    for dir in &opts.free_space {
+        if let Ok(size) = wiper.wipe_free_space(dir) {
+            total.bytes += size;
         }
-        file.write_all(value)?;
     }
-    file.sync_all()?;
-    Ok(())
+    total
 }
 
-fn wipe_loop(path: &Path, params: &mut Params) -> Result<()> {
-    if params.opts.verbose == 1 {
-        println!("[wipe] {}", path.display());
+/// Ask once for everything found by [`scan`].
+fn confirm_all(opts: &Opts, total: &Report) -> bool {
+    const MAX_PATHS: usize = 10;
+
+    eprintln!(
+        "about to wipe {} files, {} directories, {} links, {} bytes in:",
+        total.files, total.dirs, total.links, total.bytes,
+    );
+    for file in opts.files.iter().take(MAX_PATHS) {
+        eprintln!("  {}", file.display());
     }
-    for n in 0..params.opts.num_rounds + 1 {
-        if n == 0 && !params.opts.zero {
-            continue;
-        }
-        if params.opts.verbose > 1 {
-            println!("[round: {}] {}", n, path.display());
-        }
-        wipe(path, n, params)?;
+    if opts.files.len() > MAX_PATHS {
+        eprintln!("  and {} more", opts.files.len() - MAX_PATHS);
     }
-    let new_path = &path.with_file_name(params.values.choose(&mut params.rng).unwrap());
-    fs::rename(path, new_path)?;
-    fs::remove_file(new_path)?;
-    Ok(())
+    ask("continue?")
 }
 
-fn walk(path: &Path, depth: i32, params: &mut Params) -> Result<()> {
-    let path = &path.canonicalize()?;
-    if fs::metadata(path)?.is_dir() {
-        if depth > 0 && !params.opts.recursive {
-            return Ok(());
-        }
-        for entry in fs::read_dir(path)? {
-            let entry = match entry {
-                Ok(v) => v,
-                Err(err) => {
-                    params.error_counter += 1;
-                    eprintln!("{}", err);
-                    continue;
-                }
-            };
-            walk1(&entry.path(), depth + 1, params);
+fn print_event(event: Event, verbose: i32, dry_run: bool) {
+    match event {
+        Event::Wipe(path) if verbose == 1 || dry_run => println!("[wipe] {}", path.display()),
+        Event::Round(path, n) if verbose > 1 => println!("[round: {}] {}", n + 1, path.display()),
+        Event::Unlink(path) if verbose > 0 => println!("[unlink] {}", path.display()),
+        Event::RemoveDir(path) if verbose > 0 => println!("[rmdir] {}", path.display()),
+        Event::Skip(path) if verbose > 0 => println!("[skip] {}", path.display()),
+        Event::Verify(path, n, mismatches) if verbose > 0 => {
+            if mismatches == 0 {
+                println!("[verify: {}] {}: ok", n + 1, path.display())
+            } else {
+                println!(
+                    "[verify: {}] {}: {} bytes mismatch",
+                    n + 1,
+                    path.display(),
+                    mismatches
+                )
+            }
         }
-        fs::remove_dir(path)?;
-    } else {
-        wipe_loop(path, params)?;
+        _ => {}
     }
-    Ok(())
 }
 
-fn walk1(path: &Path, depth: i32, params: &mut Params) {
-    if let Err(err) = walk(path, depth, params) {
-        params.error_counter += 1;
+fn main() -> Result<()> {
+    let mut args = env::args_os().skip(1);
+    match args.next().as_deref().and_then(OsStr::to_str) {
+        Some(command::KEYGEN) => return keygen(args),
+        Some(command::VERIFY_CERT) => return verify_cert(args),
+        _ => {}
+    }
+    let opts = get_opts()?;
+    let cert_key = opts.cert_key.as_ref().map(SigningKey::load).transpose()?;
+    let needs_confirm = !opts.force
+        && !opts.interactive
+        && !opts.dry_run
+        && !opts.files.is_empty()
+        && io::stdin().is_terminal();
+    let needs_progress = opts.progress && !opts.dry_run;
+    let scanned = if needs_confirm || needs_progress {
+        scan(&opts)
+    } else {
+        Report::default()
+    };
+    if needs_confirm && !confirm_all(&opts, &scanned) {
+        return Err("aborted".into());
+    }
+    let progress = if needs_progress {
+        let rounds = opts.config.rounds().len() as u32;
+        Some(Arc::new(Mutex::new(Progress::new(
+            scanned.bytes * rounds as u64,
+            rounds,
+        ))))
+    } else {
+        None
+    };
+    let listener_progress = progress.clone();
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let listener_records = records.clone();
+    let certificate = opts.cert.as_ref().map(|_| {
+        let rounds = opts.config.rounds();
+        // --keep=zero may add a round to the scheme.
+        let scheme = match opts.scheme {
+            Some(v) if v.patterns().len() == rounds.len() => v.to_string(),
+            _ => rounds
+                .iter()
+                .map(Pattern::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        Arc::new(Mutex::new(Certificate::new(&scheme)))
+    });
+    let listener_certificate = certificate.clone();
+    let format = opts.format;
+    let dry_run = opts.dry_run;
+    let verbose = if dry_run {
+        opts.verbose.max(1)
+    } else {
+        opts.verbose
+    };
+    let mut wiper = opts.config.build().with_listener(move |event| {
+        if let Some(progress) = &listener_progress {
+            progress.lock().unwrap().update(event);
+        }
+        if let (Some(certificate), Event::Record(record)) = (&listener_certificate, event) {
+            certificate.lock().unwrap().push(record.clone());
+        }
+        match (format, event) {
+            (Some(Format::Json), Event::Record(record)) => {
+                listener_records.lock().unwrap().push(record.to_json())
+            }
+            (Some(Format::JsonLines), Event::Record(record)) => println!("{}", record.to_json()),
+            (Some(_), _) => {}
+            (None, _) => print_event(event, verbose, dry_run),
+        }
+    });
+    if opts.interactive {
+        wiper = wiper.with_confirm(|event| match event {
+            Event::Wipe(path) => ask(&format!("wipe file {}?", path.display())),
+            Event::RemoveDir(path) => ask(&format!("remove directory {}?", path.display())),
+            Event::Unlink(path) => ask(&format!("remove {}?", path.display())),
+            _ => true,
+        });
+    }
+    let mut total = Report::default();
+    for file in &opts.files {
+        total.merge(wiper.wipe_tree(file));
+    }
+    for dir in &opts.free_space {
+        match wiper.wipe_free_space(dir) {
+            Ok(_) if format.is_some() => {}
+            Ok(bytes) if dry_run => println!(
+                "{} bytes of free space would be wiped in {}",
+                bytes,
+                dir.display(),
+            ),
+            Ok(bytes) => println!("{} bytes of free space wiped in {}", bytes, dir.display()),
+            Err(err) => total.errors.push((dir.clone(), err)),
+        }
+    }
+    if let Some(progress) = &progress {
+        progress.lock().unwrap().finish();
+    }
+    if let (Some(path), Some(certificate), Some(key)) = (&opts.cert, &certificate, &cert_key) {
+        std::fs::write(path, certificate.lock().unwrap().sign(key))?;
+    }
+    for (_, err) in &total.errors {
         eprintln!("{}", err);
     }
+    match format {
+        Some(Format::Json) => println!(
+            r#"{{"records":[{}],"summary":{}}}"#,
+            records.lock().unwrap().join(","),
+            total.to_json(),
+        ),
+        Some(Format::JsonLines) => println!(r#"{{"summary":{}}}"#, total.to_json()),
+        None if dry_run && !opts.files.is_empty() => println!(
+            "{} files, {} directories, {} links, {} bytes would be wiped",
+            total.files, total.dirs, total.links, total.bytes,
+        ),
+        None => {}
+    }
+    let error_counter = total.errors.len();
+    if error_counter != 0 {
+        return Err(format!("{} errors were during wiping", error_counter).into());
+    }
+    Ok(())
 }
 
-struct Params {
-    opts: Opts,
-    values: Vec<String>,
-    rng: ThreadRng,
-    error_counter: i32,
-    zero_block: Option<Vec<u8>>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn main() -> Result<()> {
-    let params = &mut Params {
-        opts: get_opts()?,
-        values: make_values(),
-        rng: rand::thread_rng(),
-        error_counter: 0,
-        zero_block: None,
-    };
-    assert!(!params.values.is_empty());
-    if params.opts.zero {
-        params.zero_block = Some(vec![0; params.opts.block_size as usize]);
+    #[test]
+    fn size() {
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("100").unwrap(), 100);
+        assert_eq!(parse_size("4k").unwrap(), 4 << 10);
+        assert_eq!(parse_size("4K").unwrap(), 4 << 10);
+        assert_eq!(parse_size("100M").unwrap(), 100 << 20);
+        assert_eq!(parse_size("2G").unwrap(), 2 << 30);
+        assert_eq!(parse_size("3T").unwrap(), 3 << 40);
+        assert_eq!(parse_size("16777215T").unwrap(), 16777215 << 40);
+        assert!(parse_size("16777216T").is_err());
+        assert!(parse_size("18446744073709551616").is_err());
+        assert!(parse_size("1P").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("1.5M").is_err());
     }
-    for file in &params.opts.files.clone() {
-        walk1(file, 0, params);
-    }
-    if params.error_counter != 0 {
-        return Err(format!("{} errors were during wiping", params.error_counter).into());
+
+    #[test]
+    fn age() {
+        assert_eq!(parse_age("30").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_age("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_age("2H").unwrap(), Duration::from_secs(7200));
+        assert!(parse_age("1w").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("300000000000000d").is_err());
     }
-    Ok(())
 }
@@ -0,0 +1,185 @@
+//! Sources of the bytes written on each round.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+
+use crate::Error;
+
+/// Fills the block written over a file on some round.
+///
+/// The block is filled once per round and repeated up to the file size.
+pub trait PatternSource: Send {
+    fn fill(&mut self, round: u32, buf: &mut [u8]);
+
+    /// The block is kept a multiple of this length to repeat seamlessly.
+    fn period(&self) -> usize {
+        1
+    }
+}
+
+/// Randomized ASCII dicks separated by spaces.
+pub struct Ascii {
+    values: Vec<String>,
+    rng: StdRng,
+}
+
+impl Default for Ascii {
+    fn default() -> Self {
+        Self {
+            values: make_values(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl PatternSource for Ascii {
+    fn fill(&mut self, _round: u32, buf: &mut [u8]) {
+        fill_ascii(&self.values, &mut self.rng, buf);
+    }
+}
+
+/// Bytes from a cryptographically secure generator.
+pub struct Random(StdRng);
+
+impl Default for Random {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+impl PatternSource for Random {
+    fn fill(&mut self, _round: u32, buf: &mut [u8]) {
+        self.0.fill_bytes(buf);
+    }
+}
+
+/// The same byte sequence repeated, `0x00` and `0xFF` are special cases.
+pub struct Fixed(Vec<u8>);
+
+impl Fixed {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        assert!(!bytes.is_empty());
+        Self(bytes)
+    }
+}
+
+impl PatternSource for Fixed {
+    fn fill(&mut self, _round: u32, buf: &mut [u8]) {
+        for (b, v) in buf.iter_mut().zip(self.0.iter().cycle()) {
+            *b = *v;
+        }
+    }
+
+    fn period(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Built-in pattern sources, selectable by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Ascii,
+    Zero,
+    One,
+    Random,
+    Fixed(Vec<u8>),
+}
+
+impl Pattern {
+    pub fn source(&self) -> Box<dyn PatternSource> {
+        match self {
+            Self::Ascii => Box::new(Ascii::default()),
+            Self::Zero => Box::new(Fixed::new(vec![0x00])),
+            Self::One => Box::new(Fixed::new(vec![0xFF])),
+            Self::Random => Box::new(Random::default()),
+            Self::Fixed(v) => Box::new(Fixed::new(v.clone())),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ascii => f.write_str("ascii"),
+            Self::Zero => f.write_str("zero"),
+            Self::One => f.write_str("one"),
+            Self::Random => f.write_str("random"),
+            Self::Fixed(v) => {
+                f.write_str("0x")?;
+                v.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+        }
+    }
+}
+
+/// Parses `ascii`, `zero`, `one`, `random` or hex bytes like `0x55aa`.
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "ascii" => Self::Ascii,
+            "zero" => Self::Zero,
+            "one" => Self::One,
+            "random" => Self::Random,
+            _ => {
+                let hex = s
+                    .strip_prefix("0x")
+                    .ok_or_else(|| format!("unknown pattern: {}", s))?;
+                if hex.is_empty() || hex.len() % 2 != 0 || !hex.is_ascii() {
+                    return Err(format!("invalid hex pattern: {}", s).into());
+                }
+                let bytes = (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid hex pattern: {}", s))?;
+                Self::Fixed(bytes)
+            }
+        })
+    }
+}
+
+/// Fill `buf` with random `values` separated by spaces, the last one cut.
+pub(crate) fn fill_ascii<R: Rng>(values: &[String], rng: &mut R, buf: &mut [u8]) {
+    let mut pos = 0;
+    while pos < buf.len() {
+        let value = values.choose(rng).unwrap().as_bytes();
+        let n = value.len().min(buf.len() - pos);
+        buf[pos..pos + n].copy_from_slice(&value[..n]);
+        pos += n;
+        if pos < buf.len() {
+            buf[pos] = b' ';
+            pos += 1;
+        }
+    }
+}
+
+pub(crate) fn make_values() -> Vec<String> {
+    let mut res = Vec::with_capacity(62);
+    for a in 0..2 {
+        for b in 1..if a != 0 { 8 } else { 9 } {
+            for c in 0..4 {
+                let mut s = String::with_capacity(14);
+                s.push('8');
+                if a != 0 {
+                    s.push('#');
+                }
+                s.push_str("=".repeat(b).as_str());
+                s.push('D');
+                if c != 0 {
+                    s.push(' ');
+                    s.push_str("~".repeat(c).as_str());
+                }
+                res.push(s);
+            }
+        }
+    }
+    res.push("{()}".into());
+    res.push("({})".into());
+    res
+}
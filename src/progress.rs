@@ -0,0 +1,198 @@
+//! Progress line with throughput and ETA, fed by [`Event`]s.
+
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::Event;
+
+const MB: f64 = (1 << 20) as f64;
+
+/// Redraws a single line on a terminal, prints a line every
+/// [`Progress::LOG_INTERVAL`] otherwise. Everything goes to stderr.
+pub struct Progress {
+    total: u64,
+    rounds: u32,
+    done: u64,
+    /// Round and position of files being wiped, possibly at once.
+    files: HashMap<PathBuf, (u32, u64)>,
+    current: u64,
+    path: PathBuf,
+    round: u32,
+    round_size: u64,
+    start: Instant,
+    last_print: Option<Instant>,
+    is_terminal: bool,
+}
+
+impl Progress {
+    pub const DRAW_INTERVAL: Duration = Duration::from_millis(200);
+    pub const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// `total` is the number of bytes to write in all rounds of all files.
+    pub fn new(total: u64, rounds: u32) -> Self {
+        Self {
+            total,
+            rounds,
+            done: 0,
+            files: HashMap::new(),
+            current: 0,
+            path: PathBuf::new(),
+            round: 0,
+            round_size: 0,
+            start: Instant::now(),
+            last_print: None,
+            is_terminal: io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn update(&mut self, event: Event) {
+        if let Event::Progress(path, round, pos, size) = event {
+            let last = self.files.entry(path.to_path_buf()).or_default();
+            if last.0 != round {
+                *last = (round, 0);
+            }
+            self.done += pos - last.1;
+            last.1 = pos;
+            if pos == size {
+                self.files.remove(path);
+            }
+            self.path = path.to_path_buf();
+            self.round = round;
+            self.current = pos;
+            self.round_size = size;
+            self.print(false);
+        }
+    }
+
+    /// Print the final state and end the line.
+    pub fn finish(&mut self) {
+        self.print(true);
+        if self.is_terminal {
+            eprintln!();
+        }
+    }
+
+    fn print(&mut self, force: bool) {
+        let interval = if self.is_terminal {
+            Self::DRAW_INTERVAL
+        } else {
+            Self::LOG_INTERVAL
+        };
+        let now = Instant::now();
+        if !force && self.last_print.is_some_and(|v| now - v < interval) {
+            return;
+        }
+        self.last_print = Some(now);
+        let done = self.done;
+        let elapsed = (now - self.start).as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = if speed > 0.0 {
+            format_duration(self.total.saturating_sub(done) as f64 / speed)
+        } else {
+            "--:--:--".into()
+        };
+        let line = format!(
+            "[{:5.1}%] {:.1}/{:.1} MB, {:.1} MB/s, ETA {} [round {}/{}: {:.1}/{:.1} MB] {}",
+            percent(done, self.total),
+            done as f64 / MB,
+            self.total as f64 / MB,
+            speed / MB,
+            eta,
+            self.round + 1,
+            self.rounds,
+            self.current as f64 / MB,
+            self.round_size as f64 / MB,
+            self.path.display(),
+        );
+        let stderr = &mut io::stderr();
+        let _ = if self.is_terminal {
+            write!(stderr, "\r{}\x1b[K", line)
+        } else {
+            writeln!(stderr, "{}", line)
+        };
+        let _ = stderr.flush();
+    }
+}
+
+fn percent(value: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    (value as f64 * 100.0 / total as f64).min(100.0)
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn progress(events: &[(&str, u32, u64, u64)]) -> Progress {
+        let mut progress = Progress::new(1000, 2);
+        for &(path, round, pos, size) in events {
+            progress.update(Event::Progress(Path::new(path), round, pos, size));
+        }
+        progress
+    }
+
+    #[test]
+    fn rounds() {
+        let progress = progress(&[
+            ("a", 0, 100, 300),
+            ("a", 0, 300, 300),
+            ("a", 1, 200, 300),
+            ("a", 1, 300, 300),
+        ]);
+        assert_eq!(progress.done, 600);
+        assert!(progress.files.is_empty());
+    }
+
+    #[test]
+    fn holes() {
+        // Data at 0..100 and 200..250, the end reported past a last hole.
+        let progress = progress(&[("a", 0, 100, 300), ("a", 0, 250, 300), ("a", 0, 300, 300)]);
+        assert_eq!(progress.done, 300);
+        assert!(progress.files.is_empty());
+    }
+
+    #[test]
+    fn files_at_once() {
+        let progress = progress(&[
+            ("a", 0, 100, 300),
+            ("b", 0, 50, 100),
+            ("a", 0, 200, 300),
+            ("b", 0, 100, 100),
+            ("b", 1, 100, 100),
+            ("a", 0, 300, 300),
+        ]);
+        assert_eq!(progress.done, 500);
+        assert!(progress.files.is_empty());
+        assert_eq!(percent(progress.done, progress.total), 50.0);
+    }
+
+    #[test]
+    fn unfinished() {
+        let progress = progress(&[("a", 0, 100, 300), ("b", 1, 100, 300), ("a", 1, 50, 300)]);
+        assert_eq!(progress.done, 250);
+        assert_eq!(progress.files.len(), 2);
+        assert_eq!(progress.files[Path::new("a")], (1, 50));
+    }
+
+    #[test]
+    fn percent_is_capped() {
+        assert_eq!(percent(0, 0), 100.0);
+        assert_eq!(percent(3, 2), 100.0);
+        assert_eq!(percent(1, 4), 25.0);
+    }
+}
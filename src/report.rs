@@ -0,0 +1,248 @@
+//! Outcome of wiping, as totals and per target records.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::Error;
+
+/// Totals of [`Wiper::wipe_tree`](crate::Wiper::wipe_tree).
+#[derive(Debug, Default)]
+pub struct Report {
+    pub files: u64,
+    pub dirs: u64,
+    pub bytes: u64,
+    pub verified: u64,
+    /// Symbolic links, special files and hard links removed without wiping.
+    pub links: u64,
+    pub skipped: u64,
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn merge(&mut self, other: Report) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.bytes += other.bytes;
+        self.verified += other.verified;
+        self.links += other.links;
+        self.skipped += other.skipped;
+        self.errors.extend(other.errors);
+    }
+
+    pub fn to_json(&self) -> String {
+        let errors = self
+            .errors
+            .iter()
+            .map(|(path, err)| {
+                format!(
+                    r#"{{"path":{},"error":{}}}"#,
+                    json_path(path),
+                    json_string(&err.to_string()),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"files":{},"dirs":{},"bytes":{},"verified":{},"links":{},"skipped":{},"errors":[{}]}}"#,
+            self.files, self.dirs, self.bytes, self.verified, self.links, self.skipped, errors,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Device,
+    Dir,
+    Link,
+    FreeSpace,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Device => "device",
+            Self::Dir => "dir",
+            Self::Link => "link",
+            Self::FreeSpace => "free-space",
+        }
+    }
+}
+
+/// What happened to a single target, passed with
+/// [`Event::Record`](crate::Event::Record) when it is done with.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub path: PathBuf,
+    pub kind: Kind,
+    pub size: u64,
+    /// Patterns of the completed rounds.
+    pub patterns: Vec<String>,
+    /// `None` if not read back.
+    pub verified: Option<bool>,
+    pub mismatches: u64,
+    /// SHA-512 of the content before the first round, in hex.
+    pub sha512: Option<String>,
+    /// Random name the file had right before removal.
+    pub renamed: Option<PathBuf>,
+    pub removed: bool,
+    pub error: Option<String>,
+    pub started: SystemTime,
+    pub elapsed: Duration,
+}
+
+impl Record {
+    pub fn new(path: &Path, kind: Kind) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            size: 0,
+            patterns: Vec::new(),
+            verified: None,
+            mismatches: 0,
+            sha512: None,
+            renamed: None,
+            removed: false,
+            error: None,
+            started: SystemTime::now(),
+            elapsed: Duration::default(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut res = String::with_capacity(256);
+        let _ = write!(
+            res,
+            r#"{{"path":{},"kind":"{}","size":{},"patterns":[{}],"verified":{},"mismatches":{},"sha512":{},"renamed":{},"removed":{},"error":{},"started":{:.3},"elapsed":{:.3}}}"#,
+            json_path(&self.path),
+            self.kind.as_str(),
+            self.size,
+            self.patterns
+                .iter()
+                .map(|v| json_string(v))
+                .collect::<Vec<_>>()
+                .join(","),
+            self.verified
+                .map_or_else(|| "null".into(), |v| v.to_string()),
+            self.mismatches,
+            self.sha512
+                .as_deref()
+                .map_or_else(|| "null".into(), json_string),
+            self.renamed
+                .as_deref()
+                .map_or_else(|| "null".into(), json_path),
+            self.removed,
+            self.error
+                .as_deref()
+                .map_or_else(|| "null".into(), json_string),
+            self.started
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            self.elapsed.as_secs_f64(),
+        );
+        res
+    }
+}
+
+fn json_path(path: &Path) -> String {
+    json_string(&path.to_string_lossy())
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(res, "\\u{:04x}", c as u32);
+            }
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string() {
+        assert_eq!(json_string(""), r#""""#);
+        assert_eq!(json_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(
+            json_string("\n\r\t\x00\x1f\x7f"),
+            "\"\\n\\r\\t\\u0000\\u001f\x7f\""
+        );
+        assert_eq!(json_string("é/€"), "\"é/€\"");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"a\xffb\n"));
+        assert_eq!(json_path(path), "\"a\u{fffd}b\\n\"");
+    }
+
+    #[test]
+    fn record() {
+        let mut record = Record::new(Path::new("a\"b"), Kind::FreeSpace);
+        record.started = UNIX_EPOCH + Duration::from_millis(1500);
+        record.elapsed = Duration::from_millis(250);
+        assert_eq!(
+            record.to_json(),
+            r#"{"path":"a\"b","kind":"free-space","size":0,"patterns":[],"verified":null,"mismatches":0,"sha512":null,"renamed":null,"removed":false,"error":null,"started":1.500,"elapsed":0.250}"#,
+        );
+        record.kind = Kind::File;
+        record.size = 3;
+        record.patterns = vec!["random".into(), "0x00".into()];
+        record.verified = Some(false);
+        record.mismatches = 2;
+        record.sha512 = Some("ab".into());
+        record.renamed = Some("x/0\t".into());
+        record.removed = true;
+        record.error = Some("x: \"bad\"".into());
+        assert_eq!(
+            record.to_json(),
+            r#"{"path":"a\"b","kind":"file","size":3,"patterns":["random","0x00"],"verified":false,"mismatches":2,"sha512":"ab","renamed":"x/0\t","removed":true,"error":"x: \"bad\"","started":1.500,"elapsed":0.250}"#,
+        );
+    }
+
+    #[test]
+    fn report() {
+        let mut report = Report::default();
+        assert_eq!(
+            report.to_json(),
+            r#"{"files":0,"dirs":0,"bytes":0,"verified":0,"links":0,"skipped":0,"errors":[]}"#,
+        );
+        report.merge(Report {
+            files: 1,
+            dirs: 2,
+            bytes: 3,
+            verified: 4,
+            links: 5,
+            skipped: 6,
+            errors: vec![("a".into(), "x\ny".into()), ("b\\".into(), "z".into())],
+        });
+        assert_eq!(
+            report.to_json(),
+            r#"{"files":1,"dirs":2,"bytes":3,"verified":4,"links":5,"skipped":6,"errors":[{"path":"a","error":"x\ny"},{"path":"b\\","error":"z"}]}"#,
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Well-known multi-pass wipe standards.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+
+use crate::{Error, Pattern};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// DoD 5220.22-M: a byte, its complement, random.
+    Dod,
+    /// Peter Gutmann's 35 passes.
+    Gutmann,
+    /// Bruce Schneier's 7 passes: ones, zeroes, 5 x random.
+    Schneier,
+    /// German VSITR 7 passes: alternating zeroes and ones, then `0xAA`.
+    Vsitr,
+    /// NIST SP 800-88 Clear: single pass of zeroes.
+    Nist,
+}
+
+impl Scheme {
+    pub const ALL: [Scheme; 5] = [
+        Self::Dod,
+        Self::Gutmann,
+        Self::Schneier,
+        Self::Vsitr,
+        Self::Nist,
+    ];
+
+    /// Patterns of all passes in order. Random parts, like the DoD byte or
+    /// the Gutmann fixed passes order, are chosen on every call.
+    pub fn patterns(&self) -> Vec<Pattern> {
+        match self {
+            Self::Dod => {
+                let b: u8 = rand::random();
+                vec![
+                    Pattern::Fixed(vec![b]),
+                    Pattern::Fixed(vec![!b]),
+                    Pattern::Random,
+                ]
+            }
+            Self::Gutmann => {
+                let mut fixed: Vec<Pattern> =
+                    GUTMANN.iter().map(|v| Pattern::Fixed(v.to_vec())).collect();
+                fixed.shuffle(&mut rand::thread_rng());
+                let mut res = vec![Pattern::Random; 4];
+                res.extend(fixed);
+                res.extend(vec![Pattern::Random; 4]);
+                res
+            }
+            Self::Schneier => {
+                let mut res = vec![Pattern::One, Pattern::Zero];
+                res.extend(vec![Pattern::Random; 5]);
+                res
+            }
+            Self::Vsitr => {
+                let mut res = Vec::with_capacity(7);
+                for _ in 0..3 {
+                    res.push(Pattern::Zero);
+                    res.push(Pattern::One);
+                }
+                res.push(Pattern::Fixed(vec![0xAA]));
+                res
+            }
+            Self::Nist => vec![Pattern::Zero],
+        }
+    }
+}
+
+/// Fixed passes 5-31 of the Gutmann method.
+const GUTMANN: [&[u8]; 27] = [
+    &[0x55],
+    &[0xAA],
+    &[0x92, 0x49, 0x24],
+    &[0x49, 0x24, 0x92],
+    &[0x24, 0x92, 0x49],
+    &[0x00],
+    &[0x11],
+    &[0x22],
+    &[0x33],
+    &[0x44],
+    &[0x55],
+    &[0x66],
+    &[0x77],
+    &[0x88],
+    &[0x99],
+    &[0xAA],
+    &[0xBB],
+    &[0xCC],
+    &[0xDD],
+    &[0xEE],
+    &[0xFF],
+    &[0x92, 0x49, 0x24],
+    &[0x49, 0x24, 0x92],
+    &[0x24, 0x92, 0x49],
+    &[0x6D, 0xB6, 0xDB],
+    &[0xB6, 0xDB, 0x6D],
+    &[0xDB, 0x6D, 0xB6],
+];
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dod => "dod",
+            Self::Gutmann => "gutmann",
+            Self::Schneier => "schneier",
+            Self::Vsitr => "vsitr",
+            Self::Nist => "nist",
+        })
+    }
+}
+
+impl FromStr for Scheme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|v| v.to_string() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown scheme: {}", s).into())
+    }
+}
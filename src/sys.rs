@@ -0,0 +1,730 @@
+//! Platform specific helpers.
+
+#[cfg(target_os = "linux")]
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::fs::{self, File, FileType};
+use std::io::{self, Seek, SeekFrom};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::rc::Rc;
+use std::slice;
+
+/// Alignment of buffers, offsets and lengths for direct I/O.
+pub const DIRECT_ALIGN: usize = 4096;
+
+#[derive(Clone, Copy)]
+#[repr(C, align(4096))]
+struct Page([u8; DIRECT_ALIGN]);
+
+/// Bytes starting at a [`DIRECT_ALIGN`] boundary.
+#[derive(Default)]
+pub struct AlignedBuf {
+    pages: Vec<Page>,
+    len: usize,
+}
+
+impl AlignedBuf {
+    pub fn resize(&mut self, len: usize) {
+        let page = Page([0; DIRECT_ALIGN]);
+        self.pages.resize(len.div_ceil(DIRECT_ALIGN), page);
+        self.len = len;
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.pages.as_ptr().cast(), self.len) }
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.pages.as_mut_ptr().cast(), self.len) }
+    }
+}
+
+/// Open a file for writing past the page cache, see [`At::open_direct`]
+/// on Linux.
+#[cfg(any(target_os = "android", target_os = "freebsd"))]
+fn open_direct(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn open_direct(path: &Path) -> io::Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = fs::OpenOptions::new().write(true).open(path)?;
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "macos",
+    target_os = "ios",
+)))]
+fn open_direct(_path: &Path) -> io::Result<File> {
+    Err(io::Error::new(io::ErrorKind::Other, "not supported"))
+}
+
+/// Ask the kernel to forget cached pages of the file, so the next read
+/// goes to the device.
+#[cfg(target_os = "linux")]
+pub fn drop_cache(file: &File) {
+    use std::os::unix::io::AsRawFd;
+
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn drop_cache(_file: &File) {}
+
+#[cfg(unix)]
+pub fn is_block_device(file_type: &FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    file_type.is_block_device()
+}
+
+#[cfg(not(unix))]
+pub fn is_block_device(_file_type: &FileType) -> bool {
+    false
+}
+
+/// Ranges of the file that hold data, as start and end offsets up to
+/// `size`, found with `SEEK_DATA` and `SEEK_HOLE`. The whole file is one
+/// range if the filesystem can not tell.
+#[cfg(target_os = "linux")]
+pub fn data_ranges(file: &File, size: u64) -> io::Result<Vec<(u64, u64)>> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut res = Vec::new();
+    let mut pos = 0;
+    while pos < size {
+        let start = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+        if start < 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENXIO) => Ok(res),
+                Some(libc::EINVAL) if pos == 0 => Ok(vec![(0, size)]),
+                _ => Err(err),
+            };
+        }
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        pos = (end as u64).min(size);
+        if start as u64 >= pos {
+            break;
+        }
+        res.push((start as u64, pos));
+    }
+    Ok(res)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn data_ranges(_file: &File, size: u64) -> io::Result<Vec<(u64, u64)>> {
+    Ok(vec![(0, size)])
+}
+
+/// Size of a regular file or a block device, which has zero length in
+/// metadata and is measured by seeking to the end.
+pub fn size(file: &mut File) -> io::Result<u64> {
+    let metadata = file.metadata()?;
+    if !is_block_device(&metadata.file_type()) {
+        return Ok(metadata.len());
+    }
+    let size = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(size)
+}
+
+#[cfg(unix)]
+pub fn owner(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+pub fn owner(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Longest path passed to the system as is.
+#[cfg(target_os = "linux")]
+const MAX_PATH: usize = libc::PATH_MAX as usize / 2;
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    /// Parent directory of the last long path, the next one is most
+    /// likely in the same directory or below it.
+    static PARENT: RefCell<Option<(PathBuf, Rc<File>)>> = const { RefCell::new(None) };
+}
+
+/// Path of a file to work on, reached relative to a descriptor of its
+/// parent directory if it is longer than the system allows, see [`at`].
+pub struct At<'a> {
+    path: &'a Path,
+    #[cfg(target_os = "linux")]
+    dir: Option<Rc<File>>,
+    /// The file name with `dir`, the whole path without.
+    #[cfg(target_os = "linux")]
+    name: &'a Path,
+}
+
+/// If `path` is too long, open its parent directory a part at a time, each
+/// relative to the previous one, and reach the file with the `*at` calls.
+/// The parent is kept for the next path, so a walk opens each directory
+/// about once. Short paths are used as is.
+#[cfg(target_os = "linux")]
+pub fn at(path: &Path) -> io::Result<At<'_>> {
+    if path.as_os_str().len() < MAX_PATH {
+        return Ok(At {
+            path,
+            dir: None,
+            name: path,
+        });
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let dir = PARENT.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let dir = match &*cache {
+            Some((known, dir)) if parent.starts_with(known) => {
+                open_dirs(Some(dir.clone()), parent.strip_prefix(known).unwrap())?
+            }
+            _ => open_dirs(None, parent)?,
+        };
+        *cache = Some((parent.to_path_buf(), dir.clone()));
+        Ok::<_, io::Error>(dir)
+    })?;
+    Ok(At {
+        path,
+        dir: Some(dir),
+        name: name.as_ref(),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn at(path: &Path) -> io::Result<At<'_>> {
+    Ok(At { path })
+}
+
+/// Open the directories of `path` below `dir`, as many at once as fit.
+#[cfg(target_os = "linux")]
+fn open_dirs(mut dir: Option<Rc<File>>, path: &Path) -> io::Result<Rc<File>> {
+    use std::path::Component;
+
+    let mut part = PathBuf::new();
+    for component in path.components() {
+        let component: &Path = match component {
+            Component::Normal(v) => v.as_ref(),
+            Component::ParentDir => "..".as_ref(),
+            Component::RootDir => "/".as_ref(),
+            _ => continue,
+        };
+        if part.as_os_str().len() + component.as_os_str().len() >= MAX_PATH {
+            dir = Some(Rc::new(open_at(dir.as_deref(), &part)?));
+            part = PathBuf::new();
+        }
+        part.push(component);
+    }
+    match dir {
+        Some(v) if part.as_os_str().is_empty() => Ok(v),
+        _ => Ok(Rc::new(open_at(dir.as_deref(), &part)?)),
+    }
+}
+
+/// Open a directory only to reach files in it.
+#[cfg(target_os = "linux")]
+fn open_at(dir: Option<&File>, path: &Path) -> io::Result<File> {
+    let at = At {
+        path,
+        dir: None,
+        name: if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        },
+    };
+    at.open_raw(dir_fd(dir), libc::O_PATH | libc::O_DIRECTORY)
+}
+
+#[cfg(target_os = "linux")]
+fn dir_fd(dir: Option<&File>) -> libc::c_int {
+    use std::os::unix::io::AsRawFd;
+
+    dir.map_or(libc::AT_FDCWD, |v| v.as_raw_fd())
+}
+
+#[cfg(target_os = "linux")]
+fn c_path(path: &Path) -> io::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(std::ffi::CString::new(path.as_os_str().as_bytes())?)
+}
+
+#[cfg(target_os = "linux")]
+fn check(res: libc::c_int) -> io::Result<libc::c_int> {
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(res)
+}
+
+/// Drop the kept parent if it is `path` or below, `path` is going away.
+#[cfg(target_os = "linux")]
+fn forget(path: &Path) {
+    PARENT.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache
+            .as_ref()
+            .is_some_and(|(known, _)| known.starts_with(path))
+        {
+            *cache = None;
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+impl At<'_> {
+    fn fd(&self) -> libc::c_int {
+        dir_fd(self.dir.as_deref())
+    }
+
+    fn open_raw(&self, dir: libc::c_int, flags: libc::c_int) -> io::Result<File> {
+        use std::os::unix::io::FromRawFd;
+
+        let name = c_path(self.name)?;
+        let fd = check(unsafe { libc::openat(dir, name.as_ptr(), flags | libc::O_CLOEXEC) })?;
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+
+    fn open(&self, flags: libc::c_int) -> io::Result<File> {
+        self.open_raw(self.fd(), flags)
+    }
+
+    pub fn open_read(&self) -> io::Result<File> {
+        self.open(libc::O_RDONLY)
+    }
+
+    pub fn open_write(&self) -> io::Result<File> {
+        self.open(libc::O_WRONLY)
+    }
+
+    /// Open for writing past the page cache.
+    pub fn open_direct(&self) -> io::Result<File> {
+        self.open(libc::O_WRONLY | libc::O_DIRECT)
+    }
+
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        self.open(libc::O_PATH)?.metadata()
+    }
+
+    pub fn symlink_metadata(&self) -> io::Result<fs::Metadata> {
+        self.open(libc::O_PATH | libc::O_NOFOLLOW)?.metadata()
+    }
+
+    /// Names in the directory, without `.` and `..`.
+    pub fn read_dir(&self) -> io::Result<Vec<OsString>> {
+        use std::ffi::{CStr, OsStr};
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::io::IntoRawFd;
+
+        let fd = self.open(libc::O_RDONLY | libc::O_DIRECTORY)?.into_raw_fd();
+        let dir = unsafe { libc::fdopendir(fd) };
+        if dir.is_null() {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+        let mut res = Vec::new();
+        let err = loop {
+            unsafe { *libc::__errno_location() = 0 };
+            let entry = unsafe { libc::readdir(dir) };
+            if entry.is_null() {
+                break io::Error::last_os_error();
+            }
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) }.to_bytes();
+            if name != b"." && name != b".." {
+                res.push(OsStr::from_bytes(name).to_os_string());
+            }
+        };
+        unsafe { libc::closedir(dir) };
+        match err.raw_os_error() {
+            Some(0) => Ok(res),
+            _ => Err(err),
+        }
+    }
+
+    pub fn remove_file(&self) -> io::Result<()> {
+        let name = c_path(self.name)?;
+        check(unsafe { libc::unlinkat(self.fd(), name.as_ptr(), 0) })?;
+        Ok(())
+    }
+
+    pub fn remove_dir(&self) -> io::Result<()> {
+        let name = c_path(self.name)?;
+        check(unsafe { libc::unlinkat(self.fd(), name.as_ptr(), libc::AT_REMOVEDIR) })?;
+        forget(self.path);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl At<'_> {
+    pub fn open_read(&self) -> io::Result<File> {
+        File::open(self.path)
+    }
+
+    pub fn open_write(&self) -> io::Result<File> {
+        fs::OpenOptions::new().write(true).open(self.path)
+    }
+
+    /// Open for writing past the page cache.
+    pub fn open_direct(&self) -> io::Result<File> {
+        open_direct(self.path)
+    }
+
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        fs::metadata(self.path)
+    }
+
+    pub fn symlink_metadata(&self) -> io::Result<fs::Metadata> {
+        fs::symlink_metadata(self.path)
+    }
+
+    /// Names in the directory, without `.` and `..`.
+    pub fn read_dir(&self) -> io::Result<Vec<OsString>> {
+        fs::read_dir(self.path)?
+            .map(|v| v.map(|v| v.file_name()))
+            .collect()
+    }
+
+    pub fn remove_file(&self) -> io::Result<()> {
+        fs::remove_file(self.path)
+    }
+
+    pub fn remove_dir(&self) -> io::Result<()> {
+        fs::remove_dir(self.path)
+    }
+}
+
+/// Rename `from` to `to`, failing with `AlreadyExists` instead of
+/// replacing whatever `to` is.
+#[cfg(target_os = "linux")]
+pub fn rename_noreplace(from: &At, to: &At) -> io::Result<()> {
+    const RENAME_NOREPLACE: libc::c_uint = 1;
+
+    let c_from = c_path(from.name)?;
+    let c_to = c_path(to.name)?;
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            from.fd(),
+            c_from.as_ptr(),
+            to.fd(),
+            c_to.as_ptr(),
+            RENAME_NOREPLACE,
+        )
+    };
+    if res == 0 {
+        forget(from.path);
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        // Old kernels and filesystems without the flag.
+        Some(libc::ENOSYS) | Some(libc::EINVAL) => link_rename(from, to),
+        _ => Err(err),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn rename_noreplace(from: &At, to: &At) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_from = CString::new(from.path.as_os_str().as_bytes())?;
+    let c_to = CString::new(to.path.as_os_str().as_bytes())?;
+    if unsafe { libc::renamex_np(c_from.as_ptr(), c_to.as_ptr(), libc::RENAME_EXCL) } == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOTSUP) => link_rename(from, to),
+        _ => Err(err),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "ios")))]
+pub fn rename_noreplace(from: &At, to: &At) -> io::Result<()> {
+    link_rename(from, to)
+}
+
+/// Link `to` and remove `from`, linking fails if `to` exists. Directories
+/// can not be linked, they are checked right before an ordinary rename.
+#[cfg(target_os = "linux")]
+fn link_rename(from: &At, to: &At) -> io::Result<()> {
+    let c_from = c_path(from.name)?;
+    let c_to = c_path(to.name)?;
+    if from.symlink_metadata()?.is_dir() {
+        if to.symlink_metadata().is_ok() {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+        check(unsafe { libc::renameat(from.fd(), c_from.as_ptr(), to.fd(), c_to.as_ptr()) })?;
+        forget(from.path);
+        return Ok(());
+    }
+    check(unsafe { libc::linkat(from.fd(), c_from.as_ptr(), to.fd(), c_to.as_ptr(), 0) })?;
+    from.remove_file()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn link_rename(from: &At, to: &At) -> io::Result<()> {
+    let (from, to) = (from.path, to.path);
+    if fs::symlink_metadata(from)?.is_dir() {
+        if fs::symlink_metadata(to).is_ok() {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+        return fs::rename(from, to);
+    }
+    fs::hard_link(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Device and inode of the file.
+#[cfg(unix)]
+pub fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Number of names of the file.
+#[cfg(unix)]
+pub fn links(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+pub fn links(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+/// ID of the device containing the file.
+#[cfg(unix)]
+pub fn device(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Directories of kernel interfaces, never to be wiped.
+const PSEUDO_DIRS: [&str; 3] = ["/proc", "/sys", "/dev"];
+
+/// Whether the absolute `path` is in one of [`PSEUDO_DIRS`] or, on Linux,
+/// on a pseudo filesystem mounted anywhere.
+pub fn is_pseudo(path: &Path) -> bool {
+    PSEUDO_DIRS.iter().any(|v| path.starts_with(v)) || is_pseudo_fs(path)
+}
+
+#[cfg(target_os = "linux")]
+fn is_pseudo_fs(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+
+    const MAGICS: [i64; 8] = [
+        0x9fa0,     // proc
+        0x62656572, // sysfs
+        0x1cd1,     // devpts
+        0x0027e0eb, // cgroup
+        0x63677270, // cgroup2
+        0x64626720, // debugfs
+        0x73636673, // securityfs
+        0x74726163, // tracefs
+    ];
+
+    let path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    let mut stat: libc::statfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    MAGICS.contains(&(stat.f_type as i64))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_pseudo_fs(_path: &Path) -> bool {
+    false
+}
+
+/// Space available to unprivileged users on the filesystem containing `path`.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(io::ErrorKind::Other, "not supported"))
+}
+
+#[cfg(unix)]
+pub fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return String::new();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+pub fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+/// Name of the real user, or the uid if it has no name.
+#[cfg(unix)]
+pub fn username() -> String {
+    use std::ffi::CStr;
+
+    unsafe {
+        let uid = libc::getuid();
+        let pw = libc::getpwuid(uid);
+        if pw.is_null() || (*pw).pw_name.is_null() {
+            return uid.to_string();
+        }
+        CStr::from_ptr((*pw).pw_name).to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(not(unix))]
+pub fn username() -> String {
+    std::env::var("USERNAME").unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{LoopDevice, TempDir};
+
+    #[test]
+    fn rename_noreplace_keeps_target() {
+        let dir = TempDir::new();
+        let (a, b, c) = (
+            dir.path().join("a"),
+            dir.path().join("b"),
+            dir.path().join("c"),
+        );
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let err = rename_noreplace(&at(&a).unwrap(), &at(&b).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b");
+        rename_noreplace(&at(&a).unwrap(), &at(&c).unwrap()).unwrap();
+        assert!(!a.exists());
+        assert_eq!(fs::read_to_string(&c).unwrap(), "a");
+        let (d, e) = (dir.path().join("d"), dir.path().join("e"));
+        fs::create_dir(&d).unwrap();
+        fs::create_dir(&e).unwrap();
+        let err = rename_noreplace(&at(&d).unwrap(), &at(&e).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        let err = rename_noreplace(&at(&d).unwrap(), &at(&c).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn size_of_file_is_its_length() {
+        let dir = TempDir::new();
+        let path = dir.path().join("file");
+        fs::write(&path, [0; 1000]).unwrap();
+        assert_eq!(size(&mut File::open(&path).unwrap()).unwrap(), 1000);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn data_ranges_leave_out_holes() {
+        let dir = TempDir::new();
+        let path = dir.path().join("sparse");
+        let mut file = File::create(&path).unwrap();
+        file.set_len(3 << 20).unwrap();
+        file.seek(SeekFrom::Start(1 << 20)).unwrap();
+        io::Write::write_all(&mut file, &[1; 4096]).unwrap();
+        let ranges = data_ranges(&file, 3 << 20).unwrap();
+        if ranges == [(0, 3 << 20)] {
+            eprintln!("no holes on this filesystem, skipped");
+            return;
+        }
+        assert_eq!(ranges, [(1 << 20, (1 << 20) + 4096)]);
+        assert_eq!(data_ranges(&file, 1 << 20).unwrap(), []);
+        assert_eq!(
+            data_ranges(&file, (1 << 20) + 100).unwrap(),
+            [(1 << 20, (1 << 20) + 100)]
+        );
+    }
+
+    #[test]
+    fn size_of_block_device_is_found_by_seeking() {
+        let dir = TempDir::new();
+        let image = dir.path().join("image");
+        fs::write(&image, vec![0; 1 << 20]).unwrap();
+        let device = match LoopDevice::attach(&image) {
+            Some(v) => v,
+            None => return,
+        };
+        let mut file = File::open(device.path()).unwrap();
+        assert!(is_block_device(&file.metadata().unwrap().file_type()));
+        assert_eq!(file.metadata().unwrap().len(), 0);
+        assert_eq!(size(&mut file).unwrap(), 1 << 20);
+        assert_eq!(file.stream_position().unwrap(), 0);
+    }
+}
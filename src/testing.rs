@@ -0,0 +1,93 @@
+//! Helpers for tests: temporary directories, loop devices and mounts.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Directory removed with its content when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("viper-test-{}-{}", process::id(), n));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Block device backed by an image file, detached when dropped.
+pub struct LoopDevice(PathBuf);
+
+impl LoopDevice {
+    /// `None` if loop devices can not be set up here, without root or
+    /// `losetup`, the test should then pass without doing anything.
+    pub fn attach(image: &Path) -> Option<Self> {
+        let output = Command::new("losetup")
+            .args(["--find", "--show"])
+            .arg(image)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            eprintln!("no loop device, skipped");
+            return None;
+        }
+        let path = String::from_utf8(output.stdout).ok()?;
+        Some(Self(path.trim().into()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        let _ = Command::new("losetup").arg("-d").arg(&self.0).status();
+    }
+}
+
+/// Filesystem mounted on an existing directory, unmounted when dropped.
+pub struct Mount(PathBuf);
+
+impl Mount {
+    /// `None` if `fs_type` can not be mounted here, without root or
+    /// `mount`, the test should then pass without doing anything.
+    pub fn new(fs_type: &str, options: &str, target: &Path) -> Option<Self> {
+        Self::run(&["-t", fs_type, "-o", options, fs_type], target)
+    }
+
+    /// Make `source` visible at `target` as well, `None` as with [`Mount::new`].
+    pub fn bind(source: &Path, target: &Path) -> Option<Self> {
+        Self::run(&["--bind".as_ref(), source.as_os_str()], target)
+    }
+
+    fn run<S: AsRef<OsStr>>(args: &[S], target: &Path) -> Option<Self> {
+        let status = Command::new("mount").args(args).arg(target).status().ok()?;
+        if !status.success() {
+            eprintln!("no mount, skipped");
+            return None;
+        }
+        Some(Self(target.into()))
+    }
+}
+
+impl Drop for Mount {
+    fn drop(&mut self) {
+        let _ = Command::new("umount").arg(&self.0).status();
+    }
+}